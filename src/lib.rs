@@ -1 +1,2 @@
-pub mod threshold_ecdsa;
\ No newline at end of file
+pub mod threshold_ecdsa;
+pub mod threshold_schnorr;
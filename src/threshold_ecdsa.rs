@@ -1,87 +1,668 @@
 pub mod ot;
+pub mod audit;
+pub mod batch_verification;
 pub mod bedoza;
+pub mod curve;
+pub mod dkg;
+pub mod ecies;
 pub mod prime_functions;
 pub mod hashing;
+pub mod jws;
+pub mod error;
+pub mod key_derivation;
+pub mod keyring;
+pub mod policy;
+pub mod presignature_pool;
+pub mod public_key;
+pub mod signature;
+pub mod ssh;
+pub mod x509;
 
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::time::{SystemTime, UNIX_EPOCH};
 use num_bigint::{BigInt, Sign};
-use p256::elliptic_curve::point::AffineCoordinates;
-use bedoza::{party::ShareName, zp_field::{ZpField, ZpFieldElement}, Bedoza};
+use elliptic_curve::point::AffineCoordinates;
+use elliptic_curve::group::{Curve as _, Group as _};
+use elliptic_curve::generic_array::typenum::Unsigned;
+use elliptic_curve::FieldBytesSize;
+use bedoza::{party::ShareName, zp_field::{ZpField, ZpFieldElement}, Bedoza, SharingMode};
 use ot::elgamal::Group;
-use p256::ProjectivePoint;
-use crate::threshold_ecdsa::bedoza::ec_helpers::bigint_to_scalar;
-use crate::threshold_ecdsa::hashing::hash_string;
+use p256::NistP256;
+pub use ecdsa::RecoveryId;
+use crate::threshold_ecdsa::bedoza::ec_helpers::{bigint_to_scalar, pad_to_length_big_endian};
+use crate::threshold_ecdsa::hashing::{digest_to_field_element, hash_bytes, hash_reader, HashAlgorithm, InvalidDigestLength};
+use curve::SupportedCurve;
+use audit::{AuditLog, AuditOutcome, AuditRecord};
+use dkg::DkgReveal;
+use ecies::EciesError;
+use error::{KeyringError, RefreshError, SigningError, VerificationError};
+use policy::{SigningPolicy, SigningRequest};
+use presignature_pool::{Presignature, PresignaturePool};
+use keyring::{KeyEntry, Keyring, UserId};
+use key_derivation::{derive_tweak, tweak_public_key, ChainCode, DerivationError};
 
-type PublicKey = ProjectivePoint;
-type Signature = (ZpFieldElement, ZpFieldElement);
+pub use public_key::PublicKey;
+pub use signature::Signature;
 
-pub struct ThresholdECDSA {
-    bedoza: Bedoza,
+//The output of the user dependent preprocessing, i.e. (<k>, [k^-1], [sk_j'])
+pub type PreprocessedTuple = (ShareName, ShareName, ShareName);
+
+//What the parties remember about a tuple [sk_j'], i.e. the key [sk_j] and [k^-1] it was preprocessed with, and the key [sk_j] was derived from (see root_key_of)
+struct PreprocessedKey {
+    key: ShareName,
+    sk_j: ShareName,
+    k_inv: ShareName,
+}
+
+const DEFAULT_PRESIGNATURE_BATCH_SIZE: usize = 8;
+const DEFAULT_PRESIGNATURE_LOW_WATER_MARK: usize = 2;
+
+pub struct ThresholdECDSA<C: SupportedCurve = NistP256> {
+    bedoza: Bedoza<C>,
     zp_field: ZpField,
+    presignature_pool: PresignaturePool,
+    hash_algorithm: HashAlgorithm,
+    low_s_normalization: bool,
+    keyring: Keyring<C>,
+    preprocessed_keys: HashMap<ShareName, PreprocessedKey>,
+    derived_keys: HashMap<ShareName, ShareName>,
+    public_keys: HashMap<ShareName, PublicKey<C>>,
+    hedging_key: Option<ShareName>,
+    preprocessing_sessions: u64,
+    audit_log: AuditLog,
+    dkg_reveals: HashMap<ShareName, Vec<DkgReveal<C>>>,
 }
 /*
     Implementation of Threashold ECDSA according to the paper
     Note that some compuation that depends only on public information 
     (such as opened variables) is done here in this file for simplicity.
-    In a real world scenario, both parties would compute the information themselves.
+    In a real world scenario, all parties would compute the information themselves.
+    The secret key [sk_j] is either additively shared between party_count parties, all of which take part in signing,
+    or Shamir shared, such that any threshold + 1 parties can do the preprocessing and signing.
 */
 impl ThresholdECDSA {
-    pub fn new(ot_group: Group, zp_field: ZpField) -> Self {
+    //Creates an instance on P-256 where all party_count parties hold an additive share of the key
+    //The audit log is either opened from a file using AuditLog::open, or explicitly kept in memory using AuditLog::in_memory
+    pub fn new(ot_group: Group, party_count: usize, audit_log: AuditLog) -> Self {
+        Self::new_with_sharing_mode(ot_group, party_count, SharingMode::Additive, audit_log)
+    }
+
+    //Creates an instance on P-256 where any threshold + 1 of the party_count parties can sign
+    //Note that the keypair should be generated while all parties are active, such that every party receives a share of the key
+    pub fn new_shamir(ot_group: Group, party_count: usize, threshold: usize, audit_log: AuditLog) -> Self {
+        Self::new_with_sharing_mode(ot_group, party_count, SharingMode::Shamir { threshold }, audit_log)
+    }
+}
+
+/*
+    The protocol works over any prime order curve C, the field Z_n used for the shares is derived from the order n of C.
+    e.g. ThresholdECDSA::<p384::NistP384>::new_with_sharing_mode(ot_group, 3, SharingMode::Additive, AuditLog::open(path)?)
+*/
+impl<C: SupportedCurve> ThresholdECDSA<C> {
+    pub fn new_with_sharing_mode(ot_group: Group, party_count: usize, sharing_mode: SharingMode, audit_log: AuditLog) -> Self {
+        let zp_field = ZpField::from_curve_order::<C>();
         Self {
-            bedoza: Bedoza::new(ot_group, zp_field.clone()),
+            bedoza: Bedoza::new_with_sharing_mode(ot_group, zp_field.clone(), party_count, sharing_mode),
             zp_field,
+            presignature_pool: PresignaturePool::new(DEFAULT_PRESIGNATURE_BATCH_SIZE, DEFAULT_PRESIGNATURE_LOW_WATER_MARK),
+            hash_algorithm: HashAlgorithm::Sha256,
+            low_s_normalization: false,
+            keyring: Keyring::new(),
+            preprocessed_keys: HashMap::new(),
+            derived_keys: HashMap::new(),
+            public_keys: HashMap::new(),
+            hedging_key: None,
+            preprocessing_sessions: 0,
+            audit_log,
+            dkg_reveals: HashMap::new(),
         }
     }
 
-    //Generate a keypair for a specific user, i.e. ([sk_j], pk_j)
-    pub fn gen_keypair(&mut self) -> (ShareName, PublicKey) {
-        let sk = self.bedoza.rand();
-        self.bedoza.convert_ec(sk.clone());
-        let pk = self.bedoza.open_ec(sk.clone());
+    //The field Z_n of the scalars, where n is the order of the curve
+    pub fn zp_field(&self) -> &ZpField {
+        &self.zp_field
+    }
+
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    //Selects the hash function used for signing and verifying, the default is standard ECDSA-P256-SHA256
+    pub fn set_hash_algorithm(&mut self, hash_algorithm: HashAlgorithm) {
+        self.hash_algorithm = hash_algorithm;
+    }
+
+    //When enabled, all produced signatures are normalized to low-S form, i.e. s <= n/2
+    pub fn set_low_s_normalization(&mut self, enabled: bool) {
+        self.low_s_normalization = enabled;
+    }
+
+    pub fn bedoza(&self) -> &Bedoza<C> {
+        &self.bedoza
+    }
+
+    //Sets the policy a party evaluates before it releases its share of a signature, the key in a policy is the name of the shares [sk_j]
+    //Keys tweaked or derived from [sk_j] are subject to the rules of [sk_j], and count towards its limits
+    pub fn set_signing_policy(&mut self, party_index: usize, signing_policy: SigningPolicy) {
+        self.bedoza.set_signing_policy(party_index, signing_policy);
+    }
+
+    pub fn audit_log(&self) -> &AuditLog {
+        &self.audit_log
+    }
+
+    //Replaces the audit log, the nonces recorded in the previous log are no longer checked unless the new log holds them as well
+    pub fn set_audit_log(&mut self, audit_log: AuditLog) {
+        self.audit_log = audit_log;
+    }
+
+    //Selects the parties taking part in the following preprocessing and signing
+    pub fn set_active_parties(&mut self, party_indices: &[usize]) {
+        self.bedoza.set_active_parties(party_indices);
+    }
+
+    //Generate a keypair for a specific user, i.e. ([sk_j], pk_j), using the commit-then-reveal key generation in dkg.rs
+    //The first key generated becomes the hedging key, unless one has already been selected
+    pub fn gen_keypair(&mut self) -> (ShareName, PublicKey<C>) {
+        let session = self.dkg_commit();
+        let (sk, pk) = self.dkg_reveal(&session).and_then(|reveals| self.dkg_finish(session, &reveals)).expect("The parties did not follow the key generation protocol");
+        if self.hedging_key.is_none() {
+            self.hedging_key = Some(sk.clone());
+        }
 
         (sk, pk)
     }
 
+    //Generates a keypair for the user j and stores it in the keyring, a user can only have one key
+    pub fn gen_user_keypair(&mut self, user_id: &str, metadata: BTreeMap<String, String>) -> Result<PublicKey<C>, KeyringError> {
+        if self.keyring.contains(user_id) {
+            return Err(KeyringError::DuplicateUser(user_id.to_string()));
+        }
+        let (sk, pk) = self.gen_keypair();
+        self.keyring.insert(user_id.to_string(), KeyEntry { sk, pk, metadata, created_at: SystemTime::now() })?;
+        Ok(pk)
+    }
+
+    pub fn keyring(&self) -> &Keyring<C> {
+        &self.keyring
+    }
+
+    //Lists the users who have a key, in order of their ids
+    pub fn list_users(&self) -> Vec<UserId> {
+        self.keyring.list().map(|(user_id, _)| user_id.clone()).collect()
+    }
+
+    pub fn public_key(&self, user_id: &str) -> Result<PublicKey<C>, KeyringError> {
+        Ok(self.keyring.get(user_id)?.pk)
+    }
+
+    //Deletes the key of the user j, the parties erase their shares of [sk_j] and of every value sk_j can be computed from,
+    //i.e. the keys tweaked or derived from it, and the tuples [sk_j'] preprocessed using any of these (which give sk_j together with [k^-1])
+    //If it was the hedging key, the nonces are hedged using another key in the keyring from now on, see hedging_key
+    pub fn delete_user_key(&mut self, user_id: &str) -> Result<KeyEntry<C>, KeyringError> {
+        let entry = self.keyring.remove(user_id)?;
+        if self.hedging_key.as_ref() == Some(&entry.sk) {
+            self.hedging_key = None;
+        }
+        self.delete_key(&entry.sk);
+        Ok(entry)
+    }
+
+    fn delete_key(&mut self, sk_j: &ShareName) {
+        let mut keys: Vec<ShareName> = self.derived_keys.iter().filter(|(_, root)| *root == sk_j).map(|(child, _)| child.clone()).collect();
+        keys.push(sk_j.clone());
+        let tuples: Vec<ShareName> = self.preprocessed_keys.iter().filter(|(_, tuple)| tuple.key == *sk_j).map(|(sk_j_prime, _)| sk_j_prime.clone()).collect();
+        for sk_j_prime in tuples {
+            self.preprocessed_keys.remove(&sk_j_prime);
+            self.bedoza.delete(sk_j_prime);
+        }
+        for key in keys {
+            self.derived_keys.remove(&key);
+            self.public_keys.remove(&key);
+            self.bedoza.delete(key);
+        }
+    }
+
+    //Tweaks a key by a public value t, i.e. computes the child key ([sk_j + t], pk_j + t*G) without any interaction
+    //The child key is remembered together with the key it was (possibly through other child keys) derived from, such that it is deleted with that key
+    pub fn tweak_key(&mut self, sk_j: ShareName, pk: PublicKey<C>, tweak: ZpFieldElement) -> Option<(ShareName, PublicKey<C>)> {
+        let tweak = self.zp_field.create_field_element(tweak);
+        let child_pk = tweak_public_key(&pk, tweak.clone())?;
+        let root_key = self.root_key_of(&sk_j);
+        let child_sk = self.bedoza.add_const(sk_j, tweak);
+        self.bedoza.convert_ec(child_sk.clone());
+        self.derived_keys.insert(child_sk.clone(), root_key);
+        self.public_keys.insert(child_sk.clone(), child_pk);
+        Some((child_sk, child_pk))
+    }
+
+    //The key which a tweaked or derived key was derived from, a key which is not derived is its own root
+    fn root_key_of(&self, sk_j: &ShareName) -> ShareName {
+        self.derived_keys.get(sk_j).unwrap_or(sk_j).clone()
+    }
+
+    //Derives the child key with the given index as in BIP32 non-hardened derivation, the output is ([sk_child], pk_child, chain code of the child)
+    //The child key can be used for signing like any other key, i.e. using user_dependent_preprocessing and sign
+    pub fn derive_child_key(&mut self, sk_j: ShareName, pk: PublicKey<C>, chain_code: &ChainCode, index: u32) -> Result<(ShareName, PublicKey<C>, ChainCode), DerivationError> {
+        let (tweak, child_chain_code) = derive_tweak(&pk, chain_code, index, &self.zp_field)?;
+        let (child_sk, child_pk) = self.tweak_key(sk_j, pk, tweak).ok_or(DerivationError::InvalidChildKey(index))?;
+        Ok((child_sk, child_pk, child_chain_code))
+    }
+
+    //Proactively refreshes the shares of [sk_j], such that shares leaked before the refresh are useless together with shares leaked after it
+    //As in delete_user_key, this covers every value sk_j can be computed from, i.e. the keys tweaked or derived from the same key,
+    //and the tuples ([k^-1], [sk_j']) preprocessed using any of these, which can still be used for signing since their values are unchanged
+    //The presignatures waiting in the pool are refreshed as well
+    //If any of the shares cannot be refreshed (e.g. since a party holding it is not active) no share is refreshed
+    pub fn refresh_key(&mut self, sk_j: ShareName) -> Result<(), RefreshError> {
+        let root_key = self.root_key_of(&sk_j);
+        let mut shares: Vec<ShareName> = self.derived_keys.iter().filter(|(_, root)| **root == root_key).map(|(child, _)| child.clone()).collect();
+        shares.push(root_key.clone());
+        for (sk_j_prime, tuple) in self.preprocessed_keys.iter().filter(|(_, tuple)| tuple.key == root_key) {
+            shares.push(sk_j_prime.clone());
+            shares.push(tuple.k_inv.clone());
+        }
+        for presignature in self.presignature_pool.pending() {
+            shares.push(presignature.k.clone());
+            shares.push(presignature.k_inv.clone());
+        }
+        shares.sort();
+        shares.dedup();
+        for share in shares.iter() {
+            self.bedoza.share_holders(share)?;
+        }
+        for share in shares {
+            self.bedoza.refresh(share).expect("The shares were checked before refreshing");
+        }
+        Ok(())
+    }
+
+    //Threshold Diffie-Hellman, i.e. computes sk_j * P from the shares of [sk_j] without reconstructing sk_j
+    //Every party multiplies P with its share, and the EC shares are opened
+    pub fn threshold_ecdh(&mut self, sk_j: ShareName, peer_point: &PublicKey<C>) -> C::ProjectivePoint {
+        let shared_point = self.bedoza.mul_point(sk_j, peer_point.to_projective());
+        self.bedoza.open_ec(shared_point)
+    }
+
+    //Decrypts an ECIES ciphertext encrypted to pk_j (see ecies::encrypt), using threshold ECDH with the ephemeral key of the ciphertext
+    pub fn decrypt(&mut self, sk_j: ShareName, ciphertext: &[u8]) -> Result<Vec<u8>, EciesError> {
+        let ephemeral_key = ecies::parse_ephemeral_key::<C>(ciphertext)?;
+        let shared_point = self.threshold_ecdh(sk_j, &ephemeral_key);
+        ecies::decrypt_with_shared_point::<C>(&shared_point, ciphertext)
+    }
+
+    //The key whose shares the parties use to hedge their nonce contributions, None if the nonces are not hedged
+    //This is the selected hedging key if every active party holds a share of it, and otherwise the first key in the keyring which they all hold a share of,
+    //e.g. after the hedging key has been deleted, or when a party which was inactive during key generation becomes active
+    pub fn hedging_key(&self) -> Option<&ShareName> {
+        let held_by_active_parties = |sk_j: &&ShareName| self.bedoza.active_parties().iter().all(|index| self.bedoza.party(*index).holds_share(sk_j));
+        self.hedging_key.as_ref().filter(held_by_active_parties)
+            .or_else(|| self.keyring.list().map(|(_, entry)| &entry.sk).find(held_by_active_parties))
+    }
+
+    //Selects the key whose shares are used to hedge the nonces
+    //Without a hedging key the nonces are only as good as the randomness of the parties, and the presignatures in the pool are marked as not hedged
+    pub fn set_hedging_key(&mut self, sk_j: Option<ShareName>) {
+        self.hedging_key = sk_j;
+    }
+
     //The user independent preprocessing step, the output is a tuple (<k>, [k^-1])
+    //The contribution of every party to [k] and to the blinding value [a] is hedged (see bedoza/hedged.rs), and [k^-1] = (ak)^-1 * [a]
     pub fn user_independent_preprocessing(&mut self) -> (ShareName, ShareName) {
-        let (k_inverse, b, c) = self.bedoza.rand_mul();
-        let c_open = self.bedoza.open(c);
-        let c_inverse = self.zp_field.find_inverse(c_open);
-        self.bedoza.convert_ec(b.clone());
-        let k = self.bedoza.mul_const_ec(b, c_inverse);
+        let hedging_key = self.hedging_key().cloned();
+        let k = self.bedoza.hedged_rand(hedging_key.clone(), &self.preprocessing_context(b"k"));
+        let a = self.bedoza.hedged_rand(hedging_key, &self.preprocessing_context(b"a"));
+        self.preprocessing_sessions += 1;
+        let ak = self.bedoza.mul(a.clone(), k.clone());
+        let ak_open = self.bedoza.open(ak);
+        let ak_inverse = self.zp_field.find_inverse(ak_open);
+        let k_inverse = self.bedoza.mul_const(a, ak_inverse);
+        self.bedoza.convert_ec(k.clone());
         (k, k_inverse)
     }
 
+    //The session context of a preprocessing step, the counter and the time keep it from repeating, also after a restart
+    fn preprocessing_context(&self, value: &[u8]) -> Vec<u8> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos());
+        [&b"threshold ecdsa presignature"[..], value, &self.preprocessing_sessions.to_be_bytes(), &nanos.to_be_bytes()].concat()
+    }
+
+    pub fn presignature_pool(&self) -> &PresignaturePool {
+        &self.presignature_pool
+    }
+
+    //Replaces the presignature pool with an empty pool with the given sizes, presignatures which have not been handed out are discarded
+    pub fn configure_presignature_pool(&mut self, batch_size: usize, low_water_mark: usize) {
+        let mut pool = PresignaturePool::new(batch_size, low_water_mark);
+        for k in self.presignature_pool.used_nonces() {
+            pool.mark_used(k).unwrap();
+        }
+        self.presignature_pool = pool;
+    }
+
+    //Generates a batch of presignatures and adds them to the pool
+    pub fn refill_presignature_pool(&mut self) {
+        for _ in 0..self.presignature_pool.batch_size() {
+            let hedged = self.hedging_key().is_some();
+            let (k, k_inv) = self.user_independent_preprocessing();
+            self.presignature_pool.add(Presignature { k, k_inv, hedged });
+        }
+    }
+
+    //Hands out a presignature (<k>, [k^-1]) from the pool, the pool is refilled first if it has reached its low water mark
+    pub fn take_presignature(&mut self) -> Presignature {
+        if self.presignature_pool.needs_refill() {
+            self.refill_presignature_pool();
+        }
+        self.presignature_pool.take().unwrap()
+    }
+
     //The user dependent preprocessing step, the output is a tuple (<k>, [k^-1], [sk_j'])
-    pub fn user_dependent_preprocessing(&mut self, sk_j: ShareName, k: ShareName, k_inv: ShareName) -> (ShareName, ShareName, ShareName) {
-        let sk_j_prime= self.bedoza.mul(k_inv.clone(), sk_j);
+    pub fn user_dependent_preprocessing(&mut self, sk_j: ShareName, k: ShareName, k_inv: ShareName) -> PreprocessedTuple {
+        let sk_j_prime= self.bedoza.mul(k_inv.clone(), sk_j.clone());
+        //The parties remember which key [sk_j'] belongs to, such that their signing policies can be evaluated for the key
+        //For a tweaked or derived key this is the key it was derived from, otherwise a zero tweak would give a key without any rules
+        self.preprocessed_keys.insert(sk_j_prime.clone(), PreprocessedKey { key: self.root_key_of(&sk_j), sk_j, k_inv: k_inv.clone() });
         (k, k_inv, sk_j_prime)
     }
 
+    //The user dependent preprocessing step for the key of the user j in the keyring
+    pub fn user_dependent_preprocessing_for_user(&mut self, user_id: &str, k: ShareName, k_inv: ShareName) -> Result<PreprocessedTuple, KeyringError> {
+        let sk_j = self.keyring.get(user_id)?.sk.clone();
+        Ok(self.user_dependent_preprocessing(sk_j, k, k_inv))
+    }
+
+    //Signing a binary message M with the key of the user j, using a presignature from the pool
+    pub fn sign_for_user(&mut self, user_id: &str, message: &[u8]) -> Result<Signature, SigningError> {
+        let sk_j = self.keyring.get(user_id)?.sk.clone();
+        let presignature = self.take_presignature();
+        let (k, k_inv, sk_j_prime) = self.user_dependent_preprocessing(sk_j, presignature.k, presignature.k_inv);
+        self.sign_bytes(k, k_inv, sk_j_prime, message)
+    }
+
     //Signing a message M using a preproccesed tuple (k, k^-1, sk_j'), the output is a signature (r,s)
-    pub fn sign(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, message: &str) -> Signature {
-        let r = self.bedoza.open_ec(k);
-        let x = r.to_affine().x();
-        let x_as_field_elem = self.zp_field.create_field_element(BigInt::from_bytes_be(Sign::Plus, x.as_slice()));
-        let h_m = hash_string(message, self.zp_field.clone());
-        let s_left = self.bedoza.mul_const(k_inv, h_m.clone());
-        let s_right = self.bedoza.mul_const(sk_j_prime, x_as_field_elem.clone());
-        let s = self.bedoza.add(s_left, s_right);
-        let s_open = self.bedoza.open(s);
+    //Every nonce k can only be used once, a second signature using the same k is refused
+    pub fn sign(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, message: &str) -> Result<Signature, SigningError> {
+        self.sign_bytes(k, k_inv, sk_j_prime, message.as_bytes())
+    }
+
+    //Signing an arbitrary binary message M
+    pub fn sign_bytes(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, message: &[u8]) -> Result<Signature, SigningError> {
+        let h_m = hash_bytes(message, self.hash_algorithm, self.zp_field.clone());
+        self.sign_hash(k, k_inv, sk_j_prime, h_m, Some(message))
+    }
+
+    //Signing a digest which has already been computed by the caller, the digest must be computed using the selected hash function
+    pub fn sign_digest(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, digest: &[u8]) -> Result<Signature, SigningError> {
+        let h_m = digest_to_field_element(digest, self.hash_algorithm, self.zp_field.clone())?;
+        self.sign_hash(k, k_inv, sk_j_prime, h_m, None)
+    }
+
+    //Signing a message which is read (and hashed) from a stream, such that the message does not have to fit in memory
+    pub fn sign_reader<R: Read>(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, reader: R) -> Result<Signature, SigningError> {
+        let h_m = hash_reader(reader, self.hash_algorithm, self.zp_field.clone())?;
+        self.sign_hash(k, k_inv, sk_j_prime, h_m, None)
+    }
+
+    //Signing the hash H(M) of a message, which is the part of the protocol shared by all the sign functions
+    //The message is given to the signing policies of the parties, if it is known
+    fn sign_hash(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, h_m: ZpFieldElement, message: Option<&[u8]>) -> Result<Signature, SigningError> {
+        let (signature, _) = self.sign_hash_recoverable(k, k_inv, sk_j_prime, h_m, message)?;
+        Ok(signature)
+    }
+
+    //Signing data for the formats which fix the hash function to SHA-256, i.e. JWS ES256, X.509 ecdsa-with-SHA256 and SSH ecdsa-sha2-nistp256
+    //The signature is always computed using SHA-256, independent of the hash function selected for the instance
+    //If the format embeds the public key, a tuple preprocessed with another key is refused before signing, and the signature is verified under it
+    fn sign_sha256(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, data: &[u8], pk: Option<PublicKey<C>>) -> Result<Signature, SigningError> {
+        if let Some(pk) = pk {
+            self.check_public_key(&sk_j_prime, &pk)?;
+        }
+        let h_m = hash_bytes(data, HashAlgorithm::Sha256, self.zp_field.clone());
+        let signature = self.sign_hash(k, k_inv, sk_j_prime, h_m.clone(), Some(data))?;
+        if let Some(pk) = pk {
+            if self.verify_hash(pk, h_m, &signature).is_err() {
+                self.audit_log.set_outcome(&signature.r, AuditOutcome::Invalid)?;
+                return Err(SigningError::InvalidSignature);
+            }
+        }
+        Ok(signature)
+    }
+
+    //Signing a binary message M, the output is a signature (r,s) together with the recovery id v,
+    //which tells the parity of the y-coordinate of R = k*G and whether its x-coordinate was reduced mod n
+    pub fn sign_recoverable(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, message: &[u8]) -> Result<(Signature, RecoveryId), SigningError> {
+        let h_m = hash_bytes(message, self.hash_algorithm, self.zp_field.clone());
+        self.sign_hash_recoverable(k, k_inv, sk_j_prime, h_m, Some(message))
+    }
+
+    fn sign_hash_recoverable(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, h_m: ZpFieldElement, message: Option<&[u8]>) -> Result<(Signature, RecoveryId), SigningError> {
+        let key = self.key_of(&sk_j_prime)?;
+        self.presignature_pool.mark_used(&k)?;
+        let r = self.bedoza.open_ec(k).to_affine();
+        self.record_nonce(&key, &r, &h_m)?;
+        let s = self.share_of_s(k_inv, sk_j_prime, h_m, &r);
+        let s_open = self.bedoza.open_signature_shares(&[(s, SigningRequest { key: &key, message })]).pop().unwrap();
+        let r_as_field_elem = self.x_as_field_elem(&r);
+        match s_open {
+            Ok(s_open) => {
+                self.audit_log.set_outcome(&r_as_field_elem, AuditOutcome::Produced)?;
+                Ok(self.finish_signature(&r, s_open))
+            }
+            Err(refusal) => {
+                self.audit_log.set_outcome(&r_as_field_elem, AuditOutcome::Refused)?;
+                Err(refusal.into())
+            }
+        }
+    }
+
+    //Signing many messages, where entry i is signed using the preprocessed tuple (k, k^-1, sk_j') of the entry
+    //All the R values are opened in one round and all the s values in another, instead of two rounds per message
+    //The signatures are returned in the order of the entries, an entry fails if its nonce has been used, if it was not preprocessed with the key of pk
+    //(which is checked before anything is opened), or if its signature does not verify under pk
+    pub fn sign_batch(&mut self, pk: PublicKey<C>, entries: &[(PreprocessedTuple, &[u8])]) -> Vec<Result<Signature, SigningError>> {
+        let mut results: Vec<Result<Signature, SigningError>> = Vec::with_capacity(entries.len());
+        let mut signing_indices = Vec::new();
+        let mut keys = Vec::new();
+        for (index, ((k, _, sk_j_prime), _)) in entries.iter().enumerate() {
+            let checked_key = self.key_of(sk_j_prime).and_then(|key| self.check_public_key(sk_j_prime, &pk).map(|_| key));
+            match checked_key.and_then(|key| self.presignature_pool.mark_used(k).map(|_| key)) {
+                Ok(key) => {
+                    signing_indices.push(index);
+                    keys.push(key);
+                    results.push(Err(SigningError::InvalidSignature)); //Replaced below if the signature verifies
+                }
+                Err(error) => results.push(Err(error)),
+            }
+        }
+        let nonces_to_open: Vec<ShareName> = signing_indices.iter().map(|index| entries[*index].0.0.clone()).collect();
+        let opened_nonces = self.bedoza.open_ec_many(&nonces_to_open);
+
+        let mut signing_entries = Vec::new();
+        let mut s_to_open = Vec::new();
+        for ((index, r), key) in signing_indices.into_iter().zip(opened_nonces).zip(keys.iter()) {
+            let ((_, k_inv, sk_j_prime), message) = &entries[index];
+            let r = r.to_affine();
+            let h_m = hash_bytes(message, self.hash_algorithm, self.zp_field.clone());
+            if let Err(error) = self.record_nonce(key, &r, &h_m) {
+                results[index] = Err(error.into());
+                continue;
+            }
+            let s = self.share_of_s(k_inv.clone(), sk_j_prime.clone(), h_m.clone(), &r);
+            s_to_open.push((s, SigningRequest { key, message: Some(*message) }));
+            signing_entries.push((index, r, h_m));
+        }
+        let opened_s = self.bedoza.open_signature_shares(&s_to_open);
+
+        for ((index, r, h_m), s_open) in signing_entries.into_iter().zip(opened_s) {
+            let (outcome, result) = match s_open {
+                Ok(s_open) => {
+                    let (signature, _) = self.finish_signature(&r, s_open);
+                    match self.verify_hash(pk, h_m, &signature) {
+                        Ok(()) => (AuditOutcome::Produced, Ok(signature)),
+                        Err(_) => (AuditOutcome::Invalid, Err(SigningError::InvalidSignature)),
+                    }
+                }
+                Err(refusal) => (AuditOutcome::Refused, Err(refusal.into())),
+            };
+            let r_as_field_elem = self.x_as_field_elem(&r);
+            results[index] = self.audit_log.set_outcome(&r_as_field_elem, outcome).map_err(SigningError::from).and(result);
+        }
+        results
+    }
+
+    //The name of the key [sk_j] which [sk_j'] was preprocessed with (or derived from), this identifies the key in the signing policies
+    //A tuple which was not preprocessed by user_dependent_preprocessing has no known key, and is refused
+    fn key_of(&self, sk_j_prime: &ShareName) -> Result<ShareName, SigningError> {
+        self.preprocessed_keys.get(sk_j_prime).map(|tuple| tuple.key.clone()).ok_or_else(|| SigningError::UnknownKey(sk_j_prime.clone()))
+    }
+
+    //Checks that [sk_j'] was preprocessed using the key with the public key pk, before anything is opened
+    //Otherwise the parties would produce a valid signature under another key, which every party learns even though it is not output
+    fn check_public_key(&self, sk_j_prime: &ShareName, pk: &PublicKey<C>) -> Result<(), SigningError> {
+        let tuple_pk = self.preprocessed_keys.get(sk_j_prime).and_then(|tuple| self.public_keys.get(&tuple.sk_j));
+        if tuple_pk != Some(pk) {
+            return Err(SigningError::KeyMismatch(sk_j_prime.clone()));
+        }
+        Ok(())
+    }
+
+    //Records the opened nonce R in the audit log before any share of s is released, a nonce with the r of an earlier signature is refused
+    //The record is pending until the outcome of opening s is set
+    fn record_nonce(&mut self, key: &ShareName, r: &C::AffinePoint, h_m: &ZpFieldElement) -> Result<(), audit::AuditError> {
+        let x_as_field_elem = self.x_as_field_elem(r);
+        self.audit_log.append(AuditRecord { key: key.clone(), r: x_as_field_elem, message_hash: h_m.clone(), timestamp: SystemTime::now(), outcome: AuditOutcome::Pending })
+    }
+
+    //The x-coordinate of R mod n, i.e. the r of the signature
+    fn x_as_field_elem(&self, r: &C::AffinePoint) -> ZpFieldElement {
+        self.zp_field.create_field_element(BigInt::from_bytes_be(Sign::Plus, &r.x()))
+    }
 
-        (x_as_field_elem, s_open)
+    //Computes [s] = [k^-1] * H(M) + [sk_j'] * r locally, where r is the x-coordinate of R mod n
+    fn share_of_s(&mut self, k_inv: ShareName, sk_j_prime: ShareName, h_m: ZpFieldElement, r: &C::AffinePoint) -> ShareName {
+        let x_as_field_elem = self.zp_field.create_field_element(BigInt::from_bytes_be(Sign::Plus, &r.x()));
+        let s_left = self.bedoza.mul_const(k_inv, h_m);
+        let s_right = self.bedoza.mul_const(sk_j_prime, x_as_field_elem);
+        let s = self.bedoza.add(s_left.clone(), s_right.clone());
+        //The terms are erased, since sk_j' could be computed from [sk_j' * r] after the tuple has been deleted
+        self.bedoza.delete(s_left);
+        self.bedoza.delete(s_right);
+        s
+    }
+
+    //Creates the signature (r,s) and the recovery id from R and the opened s, normalizing s if enabled
+    fn finish_signature(&self, r: &C::AffinePoint, s_open: ZpFieldElement) -> (Signature, RecoveryId) {
+        let x = BigInt::from_bytes_be(Sign::Plus, &r.x());
+        let x_as_field_elem = self.zp_field.create_field_element(x.clone());
+        let is_x_reduced = x != x_as_field_elem;
+        let signature = Signature::new(x_as_field_elem, s_open);
+        if self.low_s_normalization && !signature.is_low_s(&self.zp_field) {
+            //Replacing s with n-s corresponds to using -R, which has the opposite y parity
+            let recovery_id = RecoveryId::new(!bool::from(r.y_is_odd()), is_x_reduced);
+            return (signature.normalize_s(&self.zp_field), recovery_id);
+        }
+        (signature, RecoveryId::new(r.y_is_odd().into(), is_x_reduced))
+    }
+
+    //Recovers the public key which a message was signed with, from the signature and recovery id
+    //i.e. computes pk = r^-1 * (s*R - H(M)*G), the recovered key is checked by verifying the signature
+    pub fn recover_public_key(&self, message: &[u8], signature: &Signature, recovery_id: RecoveryId) -> Result<PublicKey<C>, VerificationError> {
+        let r_point = self.recover_r_point(signature, recovery_id)?;
+        let h_m = hash_bytes(message, self.hash_algorithm, self.zp_field.clone());
+        let r_inv = self.zp_field.find_inverse(signature.r.clone());
+        let s_r = r_point * bigint_to_scalar::<C>(self.zp_field.mul(signature.s.clone(), r_inv.clone()));
+        let h_g = C::ProjectivePoint::generator() * bigint_to_scalar::<C>(self.zp_field.mul(h_m, r_inv));
+        let pk = PublicKey::from_point(s_r - h_g).map_err(|_| VerificationError::InvalidRecoveryId)?;
+
+        self.try_verify(pk, message, signature)?;
+        Ok(pk)
+    }
+
+    //Reconstructs R from its x-coordinate r and the recovery id, after checking that r and s are in range
+    fn recover_r_point(&self, signature: &Signature, recovery_id: RecoveryId) -> Result<C::ProjectivePoint, VerificationError> {
+        let in_range = |value: &ZpFieldElement| *value > BigInt::from(0) && *value < self.zp_field.p;
+        if !in_range(&signature.r) || !in_range(&signature.s) {
+            return Err(VerificationError::ScalarOutOfRange);
+        }
+
+        let mut x = signature.r.clone();
+        if recovery_id.is_x_reduced() {
+            x += &self.zp_field.p;
+        }
+        let coordinate_length = FieldBytesSize::<C>::USIZE;
+        if x.bits() > 8 * coordinate_length as u64 {
+            return Err(VerificationError::InvalidRecoveryId);
+        }
+        let mut compressed_r = vec![if recovery_id.is_y_odd() { 0x03 } else { 0x02 }];
+        compressed_r.extend(pad_to_length_big_endian(&x, coordinate_length));
+        Ok(PublicKey::<C>::from_sec1_bytes(&compressed_r).map_err(|_| VerificationError::InvalidRecoveryId)?.to_projective())
     }
 
     //Verifying a signature (r,s) on a message M using a public key pk_j
     //Note this can be done locally
-    pub fn verify_signature(&self, pk: PublicKey, message: &str, signature: Signature) -> bool {
-        let h_m = hash_string(message, self.zp_field.clone());
-        let s_inv = self.zp_field.find_inverse(signature.1.clone());
-        let left = ProjectivePoint::GENERATOR * bigint_to_scalar(self.zp_field.mul(h_m, s_inv.clone()));
-        let right = pk * bigint_to_scalar(self.zp_field.mul(s_inv.clone(), signature.0.clone()));
-        let calculated_r_x = (left + right).to_affine().x();
-        let calculated_r_x_field_elem = self.zp_field.create_field_element(BigInt::from_bytes_be(Sign::Plus, calculated_r_x.as_slice()));
+    pub fn verify_signature(&self, pk: PublicKey<C>, message: &str, signature: Signature) -> bool {
+        self.verify_bytes(pk, message.as_bytes(), signature)
+    }
 
-        calculated_r_x_field_elem == signature.0
+    //Verifying a signature (r,s) on a binary message M
+    pub fn verify_bytes(&self, pk: PublicKey<C>, message: &[u8], signature: Signature) -> bool {
+        self.try_verify(pk, message, &signature).is_ok()
     }
-}
 
+    //Verifying a signature (r,s) on a digest computed by the caller, the digest must be computed using the selected hash function
+    pub fn verify_digest(&self, pk: PublicKey<C>, digest: &[u8], signature: Signature) -> Result<bool, InvalidDigestLength> {
+        match self.try_verify_digest(pk, digest, &signature) {
+            Err(VerificationError::InvalidDigest(error)) => Err(error),
+            result => Ok(result.is_ok()),
+        }
+    }
+
+    //Verifying a signature, returning the reason if it is rejected
+    //This never panics, so it can be used for signatures and keys from untrusted sources
+    pub fn try_verify(&self, pk: PublicKey<C>, message: &[u8], signature: &Signature) -> Result<(), VerificationError> {
+        let h_m = hash_bytes(message, self.hash_algorithm, self.zp_field.clone());
+        self.verify_hash(pk, h_m, signature)
+    }
+
+    pub fn try_verify_digest(&self, pk: PublicKey<C>, digest: &[u8], signature: &Signature) -> Result<(), VerificationError> {
+        let h_m = digest_to_field_element(digest, self.hash_algorithm, self.zp_field.clone())?;
+        self.verify_hash(pk, h_m, signature)
+    }
+
+    //Verifying a DER encoded signature, non-canonical encodings are rejected
+    pub fn try_verify_der(&self, pk: PublicKey<C>, message: &[u8], der: &[u8]) -> Result<(), VerificationError> {
+        let signature = Signature::from_der(der, &self.zp_field)?;
+        self.try_verify(pk, message, &signature)
+    }
+
+    //Verifying a signature encoded as r||s
+    pub fn try_verify_raw(&self, pk: PublicKey<C>, message: &[u8], bytes: &[u8]) -> Result<(), VerificationError> {
+        let signature = Signature::from_bytes(bytes, &self.zp_field)?;
+        self.try_verify(pk, message, &signature)
+    }
+
+    fn verify_hash(&self, pk: PublicKey<C>, h_m: ZpFieldElement, signature: &Signature) -> Result<(), VerificationError> {
+        //r and s must be in [1, n-1], otherwise s has no inverse and r can never be an x-coordinate mod n
+        let in_range = |value: &ZpFieldElement| *value > BigInt::from(0) && *value < self.zp_field.p;
+        if !in_range(&signature.r) || !in_range(&signature.s) {
+            return Err(VerificationError::ScalarOutOfRange);
+        }
+
+        let s_inv = self.zp_field.find_inverse(signature.s.clone());
+        let left = C::ProjectivePoint::generator() * bigint_to_scalar::<C>(self.zp_field.mul(h_m, s_inv.clone()));
+        let right = pk.to_projective() * bigint_to_scalar::<C>(self.zp_field.mul(s_inv.clone(), signature.r.clone()));
+        let calculated_r = left + right;
+        if calculated_r.is_identity().into() {
+            return Err(VerificationError::IdentityPoint);
+        }
+        let calculated_r_x = calculated_r.to_affine().x();
+        let calculated_r_x_field_elem = self.zp_field.create_field_element(BigInt::from_bytes_be(Sign::Plus, &calculated_r_x));
+
+        if calculated_r_x_field_elem != signature.r {
+            return Err(VerificationError::SignatureMismatch);
+        }
+        Ok(())
+    }
+}
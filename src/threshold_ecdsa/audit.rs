@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use crate::threshold_ecdsa::bedoza::party::ShareName;
+use crate::threshold_ecdsa::bedoza::zp_field::ZpFieldElement;
+
+/*
+    The audit log of all signatures, which also guarantees that no two signatures ever share the same r.
+    A record is written for every opened nonce R before the parties release their shares of s, so a signature cannot exist without a record,
+    and a nonce whose r is already in the log is refused. Since R and -R have the same r, this also catches k' = -k.
+    Once s has been opened the outcome of the record is set, so a record whose nonce was refused (or whose signature turned out invalid) is not mistaken for a signature.
+    A persistent log is a file with one JSON record per line, which is read back when the log is opened, such that the check survives restarts.
+    A later line with the r of an earlier one sets the outcome of that record. The file is locked while the log is open, so two instances cannot use it at once.
+    A crash while a line is written leaves an incomplete last line, which is removed when the log is opened. Since s is only released once its record has been written,
+    the removed record belongs to a nonce without a signature, and a removed outcome leaves the record pending.
+    An in-memory log forgets every nonce when the process stops, so it only suits instances whose keys do not outlive the process.
+*/
+
+//What became of a nonce after it was recorded
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuditOutcome {
+    //R has been opened but the outcome of opening s is not recorded, e.g. since the process stopped in between, so a signature may exist
+    #[default]
+    Pending,
+    //The parties released their shares of s and the signature was output
+    Produced,
+    //A party refused to release its share of s, so there is no signature
+    Refused,
+    //s was opened but the signature does not verify, so it was not output
+    Invalid,
+}
+
+//A record of an opened nonce and its outcome, the key is identified by the name of its shares [sk_j] as in the signing policies
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    pub key: ShareName,
+    #[serde(with = "hex_bigint")]
+    pub r: ZpFieldElement,
+    #[serde(with = "hex_bigint")]
+    pub message_hash: ZpFieldElement,
+    pub timestamp: SystemTime,
+    #[serde(default)]
+    pub outcome: AuditOutcome,
+}
+
+//A query on the log, every field which is set must match, e.g. AuditQuery { key: Some(sk), ..AuditQuery::default() }
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditQuery {
+    pub key: Option<ShareName>,
+    pub message_hash: Option<ZpFieldElement>,
+    pub outcome: Option<AuditOutcome>,
+    //Records from this time on
+    pub since: Option<SystemTime>,
+    //Records before this time
+    pub until: Option<SystemTime>,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, record: &AuditRecord) -> bool {
+        self.key.as_ref().is_none_or(|key| *key == record.key)
+            && self.message_hash.as_ref().is_none_or(|message_hash| *message_hash == record.message_hash)
+            && self.outcome.is_none_or(|outcome| outcome == record.outcome)
+            && self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp < until)
+    }
+}
+
+#[derive(Debug)]
+pub struct AuditLog {
+    records: Vec<AuditRecord>,
+    //The index of the record with each r
+    record_indices: HashMap<ZpFieldElement, usize>,
+    file: Option<File>,
+}
+
+impl AuditLog {
+    //A log which is only kept in memory, the nonces are forgotten when the process stops, use open for keys which outlive the process
+    pub fn in_memory() -> Self {
+        Self { records: Vec::new(), record_indices: HashMap::new(), file: None }
+    }
+
+    //Opens a persistent log, the records already in the file are read, and new records are appended to it
+    //The file stays locked until the log is dropped, opening a file which another log holds gives an error of kind WouldBlock
+    //An incomplete last line (without a line break) is truncated from the file, any other line which is not a record gives an error of kind InvalidData
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut file = OpenOptions::new().create(true).read(true).append(true).open(path)?;
+        file.try_lock().map_err(|error| match error {
+            TryLockError::WouldBlock => io::Error::new(io::ErrorKind::WouldBlock, "the audit log is in use by another instance"),
+            TryLockError::Error(error) => error,
+        })?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        let complete_length = contents.iter().rposition(|byte| *byte == b'\n').map_or(0, |index| index + 1);
+        if complete_length < contents.len() {
+            file.set_len(complete_length as u64)?;
+            file.sync_data()?;
+        }
+        let mut log = Self::in_memory();
+        for line in contents[..complete_length].split(|byte| *byte == b'\n') {
+            if line.trim_ascii().is_empty() {
+                continue;
+            }
+            let record: AuditRecord = serde_json::from_slice(line).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+            match log.record_indices.get(&record.r) {
+                Some(index) => log.records[*index].outcome = record.outcome,
+                None => {
+                    log.record_indices.insert(record.r.clone(), log.records.len());
+                    log.records.push(record);
+                }
+            }
+        }
+        log.file = Some(file);
+        Ok(log)
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    //All records, in the order they were written
+    pub fn records(&self) -> &[AuditRecord] {
+        &self.records
+    }
+
+    pub fn contains_r(&self, r: &ZpFieldElement) -> bool {
+        self.record_indices.contains_key(r)
+    }
+
+    //The record with the given r, there is at most one
+    pub fn find_by_r(&self, r: &ZpFieldElement) -> Option<&AuditRecord> {
+        self.record_indices.get(r).map(|index| &self.records[*index])
+    }
+
+    pub fn query(&self, query: &AuditQuery) -> Vec<&AuditRecord> {
+        self.records.iter().filter(|record| query.matches(record)).collect()
+    }
+
+    //Adds a record, the record is written to the file (if any) before it is added, and a record with an r which is already in the log is refused
+    pub fn append(&mut self, record: AuditRecord) -> Result<(), AuditError> {
+        if self.contains_r(&record.r) {
+            return Err(AuditError::NonceReuse(record.r));
+        }
+        self.write(&record)?;
+        self.record_indices.insert(record.r.clone(), self.records.len());
+        self.records.push(record);
+        Ok(())
+    }
+
+    //Sets the outcome of the record with the given r, the updated record is appended to the file (if any)
+    pub fn set_outcome(&mut self, r: &ZpFieldElement, outcome: AuditOutcome) -> Result<(), AuditError> {
+        let index = *self.record_indices.get(r).ok_or_else(|| AuditError::UnknownNonce(r.clone()))?;
+        let record = AuditRecord { outcome, ..self.records[index].clone() };
+        self.write(&record)?;
+        self.records[index] = record;
+        Ok(())
+    }
+
+    fn write(&mut self, record: &AuditRecord) -> Result<(), AuditError> {
+        if let Some(file) = self.file.as_mut() {
+            let mut line = serde_json::to_vec(record).unwrap();
+            line.push(b'\n');
+            file.write_all(&line).and_then(|_| file.sync_data()).map_err(|error| AuditError::Io(error.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+//The reasons a record cannot be added to the log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditError {
+    //A signature with the same r has already been made
+    NonceReuse(ZpFieldElement),
+    //There is no record with the r whose outcome is set
+    UnknownNonce(ZpFieldElement),
+    //The record could not be written to the file
+    Io(String),
+}
+
+impl fmt::Display for AuditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditError::NonceReuse(r) => write!(f, "a signature with r = {:x} has already been made", r),
+            AuditError::UnknownNonce(r) => write!(f, "there is no record with r = {:x}", r),
+            AuditError::Io(error) => write!(f, "could not write to the audit log: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for AuditError {}
+
+//Field elements are written as hexadecimal strings, such that the log is readable
+mod hex_bigint {
+    use num_bigint::BigInt;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &BigInt, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:x}", value))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigInt, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        BigInt::parse_bytes(hex.as_bytes(), 16).ok_or_else(|| D::Error::custom("invalid hexadecimal number"))
+    }
+}
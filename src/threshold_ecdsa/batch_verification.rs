@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use num_bigint::{BigInt, RandBigInt};
+use elliptic_curve::group::Group as _;
+use p256::NistP256;
+use crate::threshold_ecdsa::bedoza::ec_helpers::multi_scalar_mul;
+use crate::threshold_ecdsa::bedoza::zp_field::ZpFieldElement;
+use crate::threshold_ecdsa::curve::SupportedCurve;
+use crate::threshold_ecdsa::error::VerificationError;
+use crate::threshold_ecdsa::hashing::hash_bytes;
+use crate::threshold_ecdsa::public_key::PublicKey;
+use crate::threshold_ecdsa::signature::Signature;
+use crate::threshold_ecdsa::{RecoveryId, ThresholdECDSA};
+
+/*
+    Batch verification of ECDSA signatures using random linear combinations.
+    A signature (r,s) on M is valid under pk if u*G + v*pk = R, where u = H(M)*s^-1, v = r*s^-1, and R is the point recovered from r using the recovery id.
+    For random 128 bit weights a_i the batch is accepted if sum_i a_i*u_i*G + sum_i a_i*v_i*pk_i - sum_i a_i*R_i is the identity,
+    which is computed as a single multi-scalar multiplication, where the terms for the same public key are merged.
+    If any signature is invalid, the combination is the identity with probability at most 2^-128.
+    A failing batch is split in halves until the invalid signatures are found, and single signatures are checked by ordinary verification,
+    such that a valid signature with a wrong recovery id is still accepted.
+*/
+
+const WEIGHT_BITS: u64 = 128;
+
+//A signature to verify in a batch, the recovery id is the one returned by sign_recoverable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchEntry<'a, C: SupportedCurve = NistP256> {
+    pub pk: PublicKey<C>,
+    pub message: &'a [u8],
+    pub signature: Signature,
+    pub recovery_id: RecoveryId,
+}
+
+//An entry with the values used in the linear combination
+struct PreparedEntry<C: SupportedCurve> {
+    index: usize,
+    u: ZpFieldElement,
+    v: ZpFieldElement,
+    r_point: C::ProjectivePoint,
+}
+
+impl<C: SupportedCurve> ThresholdECDSA<C> {
+    //Verifies many signatures at once, the messages are hashed using the selected hash function
+    //If the batch is rejected, the error lists the index of every invalid entry and the reason it was rejected, in the order of the entries
+    pub fn verify_batch(&self, entries: &[BatchEntry<C>]) -> Result<(), Vec<(usize, VerificationError)>> {
+        let mut prepared = Vec::with_capacity(entries.len());
+        let mut invalid = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            match self.recover_r_point(&entry.signature, entry.recovery_id) {
+                Ok(r_point) => {
+                    let h_m = hash_bytes(entry.message, self.hash_algorithm, self.zp_field.clone());
+                    let s_inv = self.zp_field.find_inverse(entry.signature.s.clone());
+                    let u = self.zp_field.mul(h_m, s_inv.clone());
+                    let v = self.zp_field.mul(entry.signature.r.clone(), s_inv);
+                    prepared.push(PreparedEntry { index, u, v, r_point });
+                }
+                //The signature may still be valid if only the recovery id is wrong
+                Err(VerificationError::InvalidRecoveryId) => {
+                    if let Err(error) = self.try_verify(entry.pk, entry.message, &entry.signature) {
+                        invalid.push((index, error));
+                    }
+                }
+                Err(error) => invalid.push((index, error)),
+            }
+        }
+
+        self.find_invalid_entries(entries, &prepared, &mut invalid);
+        if invalid.is_empty() {
+            return Ok(());
+        }
+        invalid.sort_by_key(|(index, _)| *index);
+        Err(invalid)
+    }
+
+    //Checks the prepared entries as one batch, and splits a failing batch in halves to find the invalid entries
+    fn find_invalid_entries(&self, entries: &[BatchEntry<C>], prepared: &[PreparedEntry<C>], invalid: &mut Vec<(usize, VerificationError)>) {
+        if prepared.is_empty() || self.check_linear_combination(entries, prepared) {
+            return;
+        }
+        if let [single] = prepared {
+            let entry = &entries[single.index];
+            if let Err(error) = self.try_verify(entry.pk, entry.message, &entry.signature) {
+                invalid.push((single.index, error));
+            }
+            return;
+        }
+        let (left, right) = prepared.split_at(prepared.len() / 2);
+        self.find_invalid_entries(entries, left, invalid);
+        self.find_invalid_entries(entries, right, invalid);
+    }
+
+    fn check_linear_combination(&self, entries: &[BatchEntry<C>], prepared: &[PreparedEntry<C>]) -> bool {
+        let mut rng = rand::thread_rng();
+        let mut generator_scalar = BigInt::from(0);
+        let mut key_terms: HashMap<Vec<u8>, (ZpFieldElement, C::ProjectivePoint)> = HashMap::new();
+        let mut terms = Vec::with_capacity(prepared.len() + 2);
+        for entry in prepared {
+            let weight: ZpFieldElement = rng.gen_biguint(WEIGHT_BITS).into();
+            generator_scalar = self.zp_field.add(generator_scalar, self.zp_field.mul(entry.u.clone(), weight.clone()));
+            let pk = entries[entry.index].pk;
+            let key_term = key_terms.entry(pk.to_sec1_bytes(true)).or_insert_with(|| (BigInt::from(0), pk.to_projective()));
+            key_term.0 = self.zp_field.add(key_term.0.clone(), self.zp_field.mul(entry.v.clone(), weight.clone()));
+            terms.push((self.zp_field.create_field_element(-weight), entry.r_point));
+        }
+        terms.push((generator_scalar, C::ProjectivePoint::generator()));
+        terms.extend(key_terms.into_values());
+        bool::from(multi_scalar_mul::<C>(&terms).is_identity())
+    }
+}
@@ -1,93 +1,291 @@
 pub mod zp_field;
 pub mod ec_helpers;
 pub mod party;
+pub mod shamir;
+pub mod backup;
+pub mod hedged;
 mod trusted_dealer;
 
 use crate::threshold_ecdsa::ot::elgamal::Group;
 use crate::threshold_ecdsa::bedoza::zp_field::{ZpField, ZpFieldElement};
 use crate::threshold_ecdsa::bedoza::party::{Party, ShareName};
+use crate::threshold_ecdsa::error::RefreshError;
 use alphabet::*;
 use num_bigint::BigInt;
-use p256::ProjectivePoint;
+use std::cell::Cell;
+use std::time::SystemTime;
+use crate::threshold_ecdsa::bedoza::backup::BackupError;
+use crate::threshold_ecdsa::curve::SupportedCurve;
+use crate::threshold_ecdsa::public_key::PublicKey;
+use crate::threshold_ecdsa::policy::{PolicyRefusal, SigningPolicy, SigningRequest};
+use elliptic_curve::group::Group as _;
+use elliptic_curve::CurveArithmetic;
+use p256::NistP256;
+use crate::threshold_ecdsa::bedoza::ec_helpers::bigint_to_scalar;
 use trusted_dealer::TrustedDealer;
 
+//The way secret values are shared between the parties
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SharingMode {
+    //The shares of all n parties sum to the secret value, so all parties are needed to open a value
+    Additive,
+    //The shares are evaluations of a polynomial of degree threshold, so any threshold + 1 parties can open a value
+    Shamir { threshold: usize },
+}
 
 /*
-    The Bedoza Protocol Arithmetic Black-box with 2 parties using Trusted Dealer for RandMul. The implementation is computationally passive secure.
-    The code inside this file represents the network and name handling of the protocol, while the parties Alice and Bob do the computation
+    The Bedoza Protocol Arithmetic Black-box with n parties using Trusted Dealer for RandMul. The implementation is computationally passive secure.
+    The code inside this file represents the network and name handling of the protocol, while the parties do the computation.
+    Values are either additively shared, i.e. the shares of all n parties sum to the secret value,
+    or Shamir shared, in which case only the currently active parties take part in the computation.
+    The EC shares are points on the curve C, which is P-256 by default, the Zp field should be the scalar field of C.
 */
-pub struct Bedoza {
-    alice: Party,
-    bob: Party,
-    share_name_generator: Box<dyn Iterator<Item = String>>,
+pub struct Bedoza<C: CurveArithmetic = NistP256> {
+    parties: Vec<Party<C>>,
+    active_parties: Vec<usize>,
+    sharing_mode: SharingMode,
+    share_name_generator: Box<dyn Iterator<Item = String> + Send>,
     zp_field: ZpField,
-    trusted_dealer: TrustedDealer
+    trusted_dealer: TrustedDealer,
+    opening_rounds: Cell<usize>,
 }
 
 impl Bedoza {
-    pub fn new(ot_group: Group, zp_group: ZpField) -> Self {
+    pub fn new(ot_group: Group, zp_group: ZpField, party_count: usize) -> Self {
+        Self::new_with_sharing_mode(ot_group, zp_group, party_count, SharingMode::Additive)
+    }
+
+    //Creates a Bedoza instance where any threshold + 1 of the party_count parties can compute on and open the shared values
+    pub fn new_shamir(ot_group: Group, zp_group: ZpField, party_count: usize, threshold: usize) -> Self {
+        Self::new_with_sharing_mode(ot_group, zp_group, party_count, SharingMode::Shamir { threshold })
+    }
+}
+
+impl<C: CurveArithmetic> Bedoza<C> {
+    //Creates a Bedoza instance for any curve C, e.g. Bedoza::<Secp256k1>::new_with_sharing_mode(...)
+    pub fn new_with_sharing_mode(ot_group: Group, zp_group: ZpField, party_count: usize, sharing_mode: SharingMode) -> Self {
+        if party_count < 2 {
+            panic!("The Bedoza protocol needs at least 2 parties");
+        }
+        if let SharingMode::Shamir { threshold } = sharing_mode {
+            if threshold == 0 || threshold >= party_count {
+                panic!("The threshold must be at least 1 and smaller than the number of parties");
+            }
+        }
         let common_group = ot_group;
         let zp_field = zp_group;
         alphabet!(LATIN = "ABCDEFGHIJKLMNOPQRSTUVWXYZ");
         let mut latin_alphabet_iterator = LATIN.iter_words();
         latin_alphabet_iterator.next(); //Skip the first element which is an empty string
         Self {
-            alice: Party::new(common_group.clone(), zp_field.clone()),
-            bob: Party::new(common_group.clone(), zp_field.clone()),
+            parties: (0..party_count).map(|_| Party::new(common_group.clone(), zp_field.clone())).collect(),
+            active_parties: (0..party_count).collect(),
+            sharing_mode,
             share_name_generator: Box::new(latin_alphabet_iterator),
             zp_field: zp_field.clone(),
-            trusted_dealer: TrustedDealer::new(zp_field)
+            trusted_dealer: TrustedDealer::new(zp_field),
+            opening_rounds: Cell::new(0),
         }
     }
 
-    //Creates a secret sharing of a specific value between the two parties, in this case the randomness is picked by Alice
-    pub fn create_secret_sharing_by_alice(&mut self, value: ZpFieldElement) -> ShareName {
-        let name = self.share_name_generator.next().unwrap();
-        let bob_share = self.alice.create_secret_share(name.clone(), value);
-        self.bob.receive_secret_share(name.clone(), bob_share);
-        name
+    //Returns the number of parties taking part in the protocol
+    pub fn party_count(&self) -> usize {
+        self.parties.len()
+    }
+
+    pub fn sharing_mode(&self) -> SharingMode {
+        self.sharing_mode
+    }
+
+    pub fn active_parties(&self) -> &[usize] {
+        &self.active_parties
+    }
+
+    //Selects the parties which take part in the following computations, e.g. the t + 1 parties which are online for signing
+    //With additive sharing all parties are always needed
+    pub fn set_active_parties(&mut self, party_indices: &[usize]) {
+        let mut active_parties = party_indices.to_vec();
+        active_parties.sort();
+        active_parties.dedup();
+        if active_parties.iter().any(|index| *index >= self.party_count()) {
+            panic!("Unknown party index");
+        }
+        match self.sharing_mode {
+            SharingMode::Additive => {
+                if active_parties.len() != self.party_count() {
+                    panic!("All parties must be active when using additive sharing");
+                }
+            }
+            SharingMode::Shamir { threshold } => {
+                if active_parties.len() <= threshold {
+                    panic!("At least threshold + 1 parties must be active");
+                }
+            }
+        }
+        self.active_parties = active_parties;
+    }
+
+    pub fn party(&self, index: usize) -> &Party<C> {
+        &self.parties[index]
+    }
+
+    pub fn party_mut(&mut self, index: usize) -> &mut Party<C> {
+        &mut self.parties[index]
     }
 
-    //Creates a secret sharing of a specific value between the two parties, in this case the randomness is picked by Bob
-    pub fn create_secret_sharing_by_bob(&mut self, value: ZpFieldElement) -> ShareName {
+    //Creates a secret sharing of a specific value between all active parties, in this case the randomness is picked by the party with the given index
+    pub fn create_secret_sharing_by_party(&mut self, dealer: usize, value: ZpFieldElement) -> ShareName {
+        if !self.active_parties.contains(&dealer) {
+            panic!("The dealer of a secret sharing must be an active party");
+        }
         let name = self.share_name_generator.next().unwrap();
-        let alice_share = self.bob.create_secret_share(name.clone(), value);
-        self.alice.receive_secret_share(name.clone(), alice_share);
+        match self.sharing_mode {
+            SharingMode::Additive => {
+                let other_party_count = self.party_count() - 1;
+                let mut other_shares = self.parties[dealer].create_secret_shares(name.clone(), value, other_party_count).into_iter();
+                for (index, party) in self.parties.iter_mut().enumerate() {
+                    if index != dealer {
+                        party.receive_secret_share(name.clone(), other_shares.next().unwrap());
+                    }
+                }
+            }
+            SharingMode::Shamir { threshold } => {
+                let shares = self.parties[dealer].create_shamir_shares(value, threshold, &self.active_parties);
+                for (index, share) in self.active_parties.iter().zip(shares) {
+                    self.parties[*index].receive_secret_share(name.clone(), share);
+                }
+            }
+        }
         name
     }
 
     //Generates a random shared value
     pub fn rand(&mut self) -> ShareName {
-        //Generating a random field element by both parties generating a random share should be fine
         let name = self.share_name_generator.next().unwrap();
-        self.alice.rand(name.clone());
-        self.bob.rand(name.clone());
+        match self.sharing_mode {
+            SharingMode::Additive => {
+                //Generating a random field element by all parties generating a random share should be fine
+                for party in self.parties.iter_mut() {
+                    party.rand(name.clone());
+                }
+            }
+            SharingMode::Shamir { threshold } => {
+                //Every active party shares a random value, the resulting value is the sum of these
+                for dealer in self.active_parties.clone() {
+                    let shares = self.parties[dealer].create_random_shamir_shares(threshold, &self.active_parties);
+                    for (index, share) in self.active_parties.iter().zip(shares) {
+                        self.parties[*index].receive_secret_share_part(name.clone(), share);
+                    }
+                }
+            }
+        }
         name
     }
 
+    //Generates a random shared value, where the contribution of every party is hedged using its share of the hedging key and the session context
+    //The context should never repeat, and the parties add their own index to it, every active party must hold a share of the hedging key
+    pub fn hedged_rand(&mut self, hedging_key: Option<ShareName>, session_context: &[u8]) -> ShareName {
+        let name = self.share_name_generator.next().unwrap();
+        let party_context = |index: usize| [session_context, &(index as u32).to_be_bytes()].concat();
+        match self.sharing_mode {
+            SharingMode::Additive => {
+                for (index, party) in self.parties.iter_mut().enumerate() {
+                    party.hedged_rand(name.clone(), hedging_key.as_deref(), &party_context(index));
+                }
+            }
+            SharingMode::Shamir { threshold } => {
+                for dealer in self.active_parties.clone() {
+                    let shares = self.parties[dealer].create_hedged_shamir_shares(hedging_key.as_deref(), &party_context(dealer), threshold, &self.active_parties);
+                    for (index, share) in self.active_parties.iter().zip(shares) {
+                        self.parties[*index].receive_secret_share_part(name.clone(), share);
+                    }
+                }
+            }
+        }
+        name
+    }
+
+    //The weights the shares of the active parties are multiplied with before adding them, when opening a value
+    fn recombination_weights(&self) -> Vec<ZpFieldElement> {
+        match self.sharing_mode {
+            SharingMode::Additive => vec![BigInt::from(1); self.active_parties.len()],
+            SharingMode::Shamir { .. } => shamir::lagrange_coefficients(&self.zp_field, &self.active_parties),
+        }
+    }
+
+    //The number of rounds of communication used for opening values so far, every call to an open function is one round
+    pub fn opening_rounds(&self) -> usize {
+        self.opening_rounds.get()
+    }
+
     //Opens a shared value
     pub fn open(&self, secret_to_open: ShareName) -> ZpFieldElement {
-        let alice_share = self.alice.open_share(secret_to_open.clone());
-        let bob_share = self.bob.open_share(secret_to_open.clone());
-        self.zp_field.add(alice_share, bob_share) //Here we add the shares in the open
-        //This should of course be done by both parties, but we have placed it here for simplicity
+        self.open_many(&[secret_to_open]).pop().unwrap()
+    }
+
+    //Opens many shared values in a single round, i.e. every party sends the shares of all the values in one message
+    pub fn open_many(&self, secrets_to_open: &[ShareName]) -> Vec<ZpFieldElement> {
+        //Here we add the (weighted) shares in the open
+        //This should of course be done by all parties, but we have placed it here for simplicity
+        self.opening_rounds.set(self.opening_rounds.get() + 1);
+        let weights = self.recombination_weights();
+        secrets_to_open.iter().map(|secret_to_open| {
+            self.active_parties.iter().zip(weights.iter()).fold(self.zp_field.create_field_element(BigInt::from(0)), |acc, (index, weight)| {
+                let share = self.parties[*index].open_share(secret_to_open.clone());
+                self.zp_field.add(acc, self.zp_field.mul(weight.clone(), share))
+            })
+        }).collect()
+    }
+
+    //Sets the signing policy of a party, which it evaluates before releasing its share of a signature
+    pub fn set_signing_policy(&mut self, party_index: usize, signing_policy: SigningPolicy) {
+        self.parties[party_index].set_signing_policy(signing_policy);
+    }
+
+    //Opens shares of signatures in a single round, every active party first checks each request against its policy
+    //A value is only opened if all active parties approve its request, otherwise the refusal of the first refusing party is returned
+    pub fn open_signature_shares(&mut self, requests: &[(ShareName, SigningRequest)]) -> Vec<Result<ZpFieldElement, PolicyRefusal>> {
+        let now = SystemTime::now();
+        let approvals: Vec<Result<(), PolicyRefusal>> = requests.iter().map(|(_, request)| self.approve_signing_request(request, now)).collect();
+        let approved: Vec<ShareName> = requests.iter().zip(approvals.iter()).filter(|(_, approval)| approval.is_ok()).map(|((name, _), _)| name.clone()).collect();
+        let mut opened = self.open_many(&approved).into_iter();
+        approvals.into_iter().map(|approval| approval.map(|_| opened.next().unwrap())).collect()
+    }
+
+    //Requests are approved one at a time, such that approved requests count towards the limits of the following requests
+    fn approve_signing_request(&mut self, request: &SigningRequest, now: SystemTime) -> Result<(), PolicyRefusal> {
+        for index in self.active_parties.clone() {
+            self.parties[index].evaluate_signing_request(request, now).map_err(|violation| PolicyRefusal { party: index, violation })?;
+        }
+        for index in self.active_parties.clone() {
+            self.parties[index].record_signature(request, now);
+        }
+        Ok(())
     }
 
     //Adds a constant to a shared value (local computation)
     pub fn add_const(&mut self, a: ShareName, constant: ZpFieldElement) -> ShareName {
-        //one party adds the constant to their share and the other party does nothing (i.e adds 0)
         let output_share = self.share_name_generator.next().unwrap();
-        self.alice.add_const(a.clone(), output_share.clone(), constant);
-        self.bob.add_const(a, output_share.clone(), self.zp_field.create_field_element(BigInt::from(0)));
+        let zero = self.zp_field.create_field_element(BigInt::from(0));
+        for index in self.active_parties.clone() {
+            let term = match self.sharing_mode {
+                //the first party adds the constant to their share and the other parties do nothing (i.e add 0)
+                SharingMode::Additive => if index == 0 { constant.clone() } else { zero.clone() },
+                //all parties add the constant, since this shifts the constant term of the polynomial
+                SharingMode::Shamir { .. } => constant.clone(),
+            };
+            self.parties[index].add_const(a.clone(), output_share.clone(), term);
+        }
         output_share
     }
 
     //Multiplies a shared value with a constant (local computation)
     pub fn mul_const(&mut self, a: ShareName, constant: ZpFieldElement) -> ShareName {
-        //both parties multiplies their share with the constant
+        //all parties multiply their share with the constant
         let output_share = self.share_name_generator.next().unwrap();
-        self.alice.mul_const(a.clone(), output_share.clone(), constant.clone());
-        self.bob.mul_const(a, output_share.clone(), constant);
+        for index in self.active_parties.clone() {
+            self.parties[index].mul_const(a.clone(), output_share.clone(), constant.clone());
+        }
         output_share
     }
 
@@ -96,16 +294,16 @@ impl Bedoza {
     pub fn local_const_mul(&mut self, a: ShareName, b: ShareName, x: ZpFieldElement, y: ZpFieldElement) -> ShareName {
         let left_term = self.mul_const(a.clone(), x);
         let right_term = self.mul_const(b.clone(), y);
-        let res = self.add(left_term, right_term);
-        res
+        self.add(left_term, right_term)
     }
 
     //Adds two shared values together (local computation)
     pub fn add(&mut self, a: ShareName, b: ShareName) -> ShareName {
-        //both parties add their shares
+        //all parties add their shares
         let output_share = self.share_name_generator.next().unwrap();
-        self.alice.add(a.clone(), b.clone(), output_share.clone());
-        self.bob.add(a, b, output_share.clone());
+        for index in self.active_parties.clone() {
+            self.parties[index].add(a.clone(), b.clone(), output_share.clone());
+        }
         output_share
     }
 
@@ -115,27 +313,34 @@ impl Bedoza {
         let v = self.share_name_generator.next().unwrap();
         let w = self.share_name_generator.next().unwrap();
 
-        let uvw = self.trusted_dealer.generate_uvw();
-        
-        //Distribution of the shares
-        self.alice.receive_secret_share(u.clone(), uvw.u.0);
-        self.bob.receive_secret_share(u.clone(), uvw.u.1);
-        
-        self.alice.receive_secret_share(v.clone(), uvw.v.0);
-        self.bob.receive_secret_share(v.clone(), uvw.v.1);
+        let uvw = self.trusted_dealer.generate_uvw(self.sharing_mode, self.party_count());
 
-        self.alice.receive_secret_share(w.clone(), uvw.w.0);
-        self.bob.receive_secret_share(w.clone(), uvw.w.1);
+        //Distribution of the shares, only the active parties receive theirs
+        for index in self.active_parties.clone() {
+            let party = &mut self.parties[index];
+            party.receive_secret_share(u.clone(), uvw.u[index].clone());
+            party.receive_secret_share(v.clone(), uvw.v[index].clone());
+            party.receive_secret_share(w.clone(), uvw.w[index].clone());
+        }
 
         (u, v, w)
     }
 
     //Generates a new secret shared value which is the product of two previously shared values
     pub fn mul(&mut self, x: ShareName, y: ShareName) -> ShareName {
+        match self.sharing_mode {
+            //With enough active parties the product of the shares can be interpolated, so no triple is needed
+            SharingMode::Shamir { threshold } if self.active_parties.len() > 2 * threshold => self.mul_with_degree_reduction(x, y, threshold),
+            //Otherwise we use a multiplication triple, since every step is linear this also keeps the degree of Shamir shares at t
+            _ => self.mul_with_triple(x, y),
+        }
+    }
+
+    fn mul_with_triple(&mut self, x: ShareName, y: ShareName) -> ShareName {
         //variables renamed to match lecture notes
         let (u, v, w) = self.rand_mul();
-        let d: ShareName = self.add(x.clone(), u);
-        let e: ShareName = self.add(y.clone(), v);
+        let d: ShareName = self.add(x.clone(), u.clone());
+        let e: ShareName = self.add(y.clone(), v.clone());
         let d_value: ZpFieldElement = self.open(d.clone());
         let e_value: ZpFieldElement = self.open(e.clone());
 
@@ -145,32 +350,144 @@ impl Bedoza {
         let ed: ZpFieldElement = self.zp_field.mul(e_value.clone(), d_value.clone());
 
         //Adding the terms
-        let wex: ShareName = self.add(w.clone(), ex);
-        let wexdy: ShareName = self.add(wex, dy);
-        let z: ShareName = self.add_const(wexdy, -ed);
+        let wex: ShareName = self.add(w.clone(), ex.clone());
+        let wexdy: ShareName = self.add(wex.clone(), dy.clone());
+        let z: ShareName = self.add_const(wexdy.clone(), -ed);
+
+        //The intermediate values are erased, otherwise e.g. the shares of e - v = y would outlive a deletion of y
+        for intermediate in [u, v, w, d, e, ex, dy, wex, wexdy] {
+            self.delete(intermediate);
+        }
         z
     }
 
+    //Multiplies Shamir shares locally, which gives shares of degree 2t, and reduces the degree back to t
+    //This is done by every party resharing its product with degree t, and everyone interpolating the received shares in 0
+    fn mul_with_degree_reduction(&mut self, x: ShareName, y: ShareName, threshold: usize) -> ShareName {
+        let z = self.share_name_generator.next().unwrap();
+        let weights = shamir::lagrange_coefficients(&self.zp_field, &self.active_parties);
+        for (dealer, weight) in self.active_parties.clone().into_iter().zip(weights) {
+            let shares = self.parties[dealer].reshare_product(x.clone(), y.clone(), threshold, &self.active_parties);
+            for (index, share) in self.active_parties.iter().zip(shares) {
+                //The weighting is done by the receiver, but is placed here for simplicity
+                self.parties[*index].receive_secret_share_part(z.clone(), self.zp_field.mul(weight.clone(), share));
+            }
+        }
+        z
+    }
+
+    //Re-randomizes the shares of a shared value (both the Zp and the EC shares), without changing the value
+    //Every party holding a share deals a sharing of zero among these parties, which they add to their shares, such that old shares become useless
+    //This needs every party holding a share to be active, since parties not taking part would be left with old shares
+    pub fn refresh(&mut self, a: ShareName) -> Result<(), RefreshError> {
+        let holders = self.share_holders(&a)?;
+        for dealer in holders.iter() {
+            let zero_shares = self.parties[*dealer].create_zero_shares(self.sharing_mode, &holders);
+            for (index, zero_share) in holders.iter().zip(zero_shares) {
+                self.parties[*index].refresh_share(a.clone(), zero_share);
+            }
+        }
+        Ok(())
+    }
+
+    //The parties holding a share of the value, if they are all active such that the shares can be refreshed
+    pub fn share_holders(&self, a: &ShareName) -> Result<Vec<usize>, RefreshError> {
+        let holders: Vec<usize> = (0..self.party_count()).filter(|index| self.parties[*index].holds_share(a)).collect();
+        if holders.is_empty() {
+            return Err(RefreshError::UnknownShare(a.clone()));
+        }
+        match holders.iter().find(|index| !self.active_parties.contains(index)) {
+            Some(party) => Err(RefreshError::InactiveParty { share: a.clone(), party: *party }),
+            None => Ok(holders),
+        }
+    }
+
+    //Erases the shares of a value held by all parties, e.g. when a key is deleted
+    pub fn delete(&mut self, a: ShareName) {
+        for party in self.parties.iter_mut() {
+            party.delete_share(&a);
+        }
+    }
+
     //Converts a shared value from zp to elliptic curve, under the same name
     pub fn convert_ec(&mut self, a: ShareName) {
-        //We convert by simply calling convert on both parties, since we have homomorphic properties between the groups
-        self.alice.convert_to_ec_shares(a.clone());
-        self.bob.convert_to_ec_shares(a);
+        //We convert by simply calling convert on all parties, since we have homomorphic properties between the groups
+        for index in self.active_parties.clone() {
+            self.parties[index].convert_to_ec_shares(a.clone());
+        }
+    }
+
+    //Computes an EC sharing of a * P for a public point P, under a new name (local computation)
+    pub fn mul_point(&mut self, a: ShareName, point: C::ProjectivePoint) -> ShareName {
+        let output_share = self.share_name_generator.next().unwrap();
+        for index in self.active_parties.clone() {
+            self.parties[index].mul_point_by_share(a.clone(), output_share.clone(), point);
+        }
+        output_share
     }
 
     //Opens a shared elliptic curve point
-    pub fn open_ec(&self, a: ShareName) -> ProjectivePoint {
-        let alice_share = self.alice.open_ec_share(a.clone());
-        let bob_share = self.bob.open_ec_share(a);
-        alice_share + bob_share //note this is addition in the elliptic curve group
+    pub fn open_ec(&self, a: ShareName) -> C::ProjectivePoint {
+        self.open_ec_many(&[a]).pop().unwrap()
+    }
+
+    //Opens many shared elliptic curve points in a single round
+    pub fn open_ec_many(&self, points_to_open: &[ShareName]) -> Vec<C::ProjectivePoint> {
+        //note this is (weighted) addition in the elliptic curve group
         //Note again we do this addition in the open for simplicity
+        self.opening_rounds.set(self.opening_rounds.get() + 1);
+        let weights: Vec<C::Scalar> = self.recombination_weights().into_iter().map(bigint_to_scalar::<C>).collect();
+        points_to_open.iter().map(|a| {
+            self.active_parties.iter().zip(weights.iter()).fold(C::ProjectivePoint::identity(), |acc, (index, weight)| {
+                acc + self.parties[*index].open_ec_share(a.clone()) * *weight
+            })
+        }).collect()
+    }
+
+    //Combines EC shares which have been revealed by the active parties (in the order of the active parties), as done when opening
+    pub fn combine_ec_shares(&self, ec_shares: &[C::ProjectivePoint]) -> C::ProjectivePoint {
+        if ec_shares.len() != self.active_parties.len() {
+            panic!("Every active party must reveal exactly one EC share");
+        }
+        let weights = self.recombination_weights().into_iter().map(bigint_to_scalar::<C>);
+        ec_shares.iter().zip(weights).fold(C::ProjectivePoint::identity(), |acc, (ec_share, weight)| acc + *ec_share * weight)
     }
 
     pub fn mul_const_ec(&mut self, a: ShareName, constant: ZpFieldElement) -> ShareName {
-        //both parties multiplies their share with the constant (ec)
+        //all parties multiply their share with the constant (ec)
         let output_share = self.share_name_generator.next().unwrap();
-        self.alice.mul_const_ec(a.clone(), output_share.clone(), constant.clone());
-        self.bob.mul_const_ec(a, output_share.clone(), constant);
+        for index in self.active_parties.clone() {
+            self.parties[index].mul_const_ec(a.clone(), output_share.clone(), constant.clone());
+        }
         output_share
     }
 }
+
+impl<C: SupportedCurve> Bedoza<C> {
+    //Restores the shares of a party from a backup, e.g. after the party has lost its shares
+    //The restored share is combined with the EC shares of the other active parties and checked against the public key in the backup,
+    //and the party only stores it if the check succeeds, such that a bad backup never replaces a share the party still holds
+    pub fn restore_share(&mut self, party_index: usize, encrypted: &[u8], passphrase: &str) -> Result<ShareName, BackupError> {
+        if !self.active_parties.contains(&party_index) {
+            panic!("The party must be active to check its restored share");
+        }
+        let share_backup = self.parties[party_index].import_share_backup(party_index, encrypted, passphrase)?;
+        let public_key = PublicKey::<C>::from_sec1_bytes(&share_backup.public_key).map_err(|_| BackupError::Malformed)?;
+        if self.active_parties.iter().any(|index| *index != party_index && !self.parties[*index].holds_ec_share(&share_backup.share_name)) {
+            return Err(BackupError::UnknownShare(share_backup.share_name));
+        }
+        self.opening_rounds.set(self.opening_rounds.get() + 1);
+        let ec_shares: Vec<C::ProjectivePoint> = self.active_parties.iter().map(|index| {
+            if *index == party_index {
+                C::ProjectivePoint::generator() * bigint_to_scalar::<C>(share_backup.share.clone())
+            } else {
+                self.parties[*index].open_ec_share(share_backup.share_name.clone())
+            }
+        }).collect();
+        if self.combine_ec_shares(&ec_shares) != public_key.to_projective() {
+            return Err(BackupError::PublicKeyMismatch);
+        }
+        self.parties[party_index].store_restored_share(&share_backup);
+        Ok(share_backup.share_name)
+    }
+}
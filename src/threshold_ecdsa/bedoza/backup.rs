@@ -0,0 +1,124 @@
+use std::fmt;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use num_bigint::BigInt;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use crate::threshold_ecdsa::bedoza::party::ShareName;
+
+/*
+    Encrypted backups of the shares a party holds of a key.
+    The backup is MAGIC || version || PBKDF2 iterations || salt || nonce || AES-256-GCM(JSON of ShareBackup) || tag,
+    where the AES key is derived from the passphrase using PBKDF2-HMAC-SHA256, and the header is authenticated together with the ciphertext.
+*/
+
+const MAGIC: &[u8; 4] = b"CCBK";
+const VERSION: u8 = 1;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+const HEADER_LENGTH: usize = MAGIC.len() + 1 + 4 + SALT_LENGTH;
+pub const DEFAULT_PBKDF2_ITERATIONS: u32 = 100_000;
+//The iteration count is read from the unauthenticated header, so it is bounded such that a tampered backup cannot keep the party busy
+pub const MAX_PBKDF2_ITERATIONS: u32 = 10 * DEFAULT_PBKDF2_ITERATIONS;
+
+//The content of a backup, the EC share and the public key are SEC1 encoded, such that a restored share can be checked
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ShareBackup {
+    pub party_index: usize,
+    pub share_name: ShareName,
+    pub share: BigInt,
+    pub ec_share: Vec<u8>,
+    pub public_key: Vec<u8>,
+}
+
+//The reasons a backup can be rejected when restoring it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackupError {
+    //The backup is not in the backup format
+    Malformed,
+    //The backup was made by a newer version
+    UnsupportedVersion(u8),
+    //The PBKDF2 iteration count in the header is above MAX_PBKDF2_ITERATIONS
+    TooManyIterations(u32),
+    //The passphrase is wrong or the backup has been tampered with
+    DecryptionFailed,
+    //The backup belongs to another party
+    WrongParty { expected: usize, actual: usize },
+    //The share in the backup is out of range or does not match the EC share in the backup
+    InconsistentShare,
+    //The other parties hold no share of the value in the backup, e.g. since it has been deleted
+    UnknownShare(ShareName),
+    //The restored share does not combine with the shares of the other parties to the public key in the backup
+    PublicKeyMismatch,
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::Malformed => write!(f, "the backup is malformed"),
+            BackupError::UnsupportedVersion(version) => write!(f, "backup version {} is not supported", version),
+            BackupError::TooManyIterations(iterations) => write!(f, "{} PBKDF2 iterations is more than the maximum of {}", iterations, MAX_PBKDF2_ITERATIONS),
+            BackupError::DecryptionFailed => write!(f, "wrong passphrase or the backup has been tampered with"),
+            BackupError::WrongParty { expected, actual } => write!(f, "the backup belongs to party {}, not party {}", actual, expected),
+            BackupError::InconsistentShare => write!(f, "the share does not match the EC share in the backup"),
+            BackupError::UnknownShare(share) => write!(f, "the other parties hold no share of {}", share),
+            BackupError::PublicKeyMismatch => write!(f, "the restored share does not match the public key"),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+//Encrypts a backup under a passphrase, using PBKDF2 with the given number of iterations (at most MAX_PBKDF2_ITERATIONS)
+pub fn encrypt_backup(backup: &ShareBackup, passphrase: &str, iterations: u32) -> Vec<u8> {
+    if iterations == 0 || iterations > MAX_PBKDF2_ITERATIONS {
+        panic!("The number of PBKDF2 iterations must be between 1 and {}", MAX_PBKDF2_ITERATIONS);
+    }
+    let mut salt = [0u8; SALT_LENGTH];
+    let mut nonce = [0u8; NONCE_LENGTH];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut output = MAGIC.to_vec();
+    output.push(VERSION);
+    output.extend(iterations.to_be_bytes());
+    output.extend(salt);
+    let plaintext = serde_json::to_vec(backup).unwrap();
+    let cipher = derive_cipher(passphrase, &salt, iterations);
+    let ciphertext = cipher.encrypt(&Nonce::from(nonce), Payload { msg: &plaintext, aad: &output }).unwrap();
+    output.extend(nonce);
+    output.extend(ciphertext);
+    output
+}
+
+//Decrypts a backup, the content is not checked against any shares here
+pub fn decrypt_backup(encrypted: &[u8], passphrase: &str) -> Result<ShareBackup, BackupError> {
+    if encrypted.len() < HEADER_LENGTH + NONCE_LENGTH || &encrypted[..MAGIC.len()] != MAGIC {
+        return Err(BackupError::Malformed);
+    }
+    let version = encrypted[MAGIC.len()];
+    if version != VERSION {
+        return Err(BackupError::UnsupportedVersion(version));
+    }
+    let iterations = u32::from_be_bytes(encrypted[MAGIC.len() + 1..MAGIC.len() + 5].try_into().unwrap());
+    if iterations == 0 {
+        return Err(BackupError::Malformed);
+    }
+    if iterations > MAX_PBKDF2_ITERATIONS {
+        return Err(BackupError::TooManyIterations(iterations));
+    }
+    let (header, rest) = encrypted.split_at(HEADER_LENGTH);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+    let nonce: [u8; NONCE_LENGTH] = nonce.try_into().unwrap();
+
+    let cipher = derive_cipher(passphrase, &header[HEADER_LENGTH - SALT_LENGTH..], iterations);
+    let plaintext = cipher.decrypt(&Nonce::from(nonce), Payload { msg: ciphertext, aad: header }).map_err(|_| BackupError::DecryptionFailed)?;
+    serde_json::from_slice(&plaintext).map_err(|_| BackupError::Malformed)
+}
+
+fn derive_cipher(passphrase: &str, salt: &[u8], iterations: u32) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    Aes256Gcm::new_from_slice(&key).unwrap()
+}
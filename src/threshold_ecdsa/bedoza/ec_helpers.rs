@@ -1,24 +1,93 @@
-use num_bigint::BigInt;
-use p256::elliptic_curve::scalar::FromUintUnchecked;
-use p256::elliptic_curve::sec1::ToEncodedPoint;
-use p256::{ProjectivePoint, U256, Scalar};
+use elliptic_curve::ff::PrimeField;
+use elliptic_curve::group::{Curve as _, Group as _};
+use elliptic_curve::generic_array::typenum::Unsigned;
+use elliptic_curve::sec1::{ModulusSize, ToEncodedPoint};
+use elliptic_curve::{AffinePoint, Curve, CurveArithmetic, FieldBytes, FieldBytesEncoding, FieldBytesSize};
+use num_bigint::{BigInt, Sign};
+use crate::threshold_ecdsa::curve::SupportedCurve;
+use crate::threshold_ecdsa::public_key::PublicKey;
 
-pub fn print_elliptic_curve_point(point: &ProjectivePoint) {
+pub fn print_elliptic_curve_point<C>(point: &C::ProjectivePoint)
+where
+    C: CurveArithmetic,
+    AffinePoint<C>: ToEncodedPoint<C>,
+    FieldBytesSize<C>: ModulusSize,
+{
     let encoded_point = point.to_affine().to_encoded_point(false);
     println!("Encoded point x: {:?}",encoded_point.x());
     println!("Encoded point y: {:?}",encoded_point.y());
 }
 
-fn pad_to_32_bytes_big_endian(value: &BigInt) -> Vec<u8> {
+pub fn pad_to_length_big_endian(value: &BigInt, length: usize) -> Vec<u8> {
     let mut bytes = value.to_bytes_be().1;
-    while bytes.len() < 32 {
-        bytes.insert(0, 0); //Prepend zeroes to reach 32 bytes
+    while bytes.len() < length {
+        bytes.insert(0, 0); //Prepend zeroes to reach the length
     }
     bytes
 }
 
-pub fn bigint_to_scalar(value: BigInt) -> Scalar {
-    let u256_int = U256::from_be_slice(&pad_to_32_bytes_big_endian(&value));
-    let result = Scalar::from_uint_unchecked(u256_int);
+//The SEC1 encoding of a point, where the identity point (which has no public key encoding) is encoded as 0x00
+pub fn encode_point<C: SupportedCurve>(point: &C::ProjectivePoint, compressed: bool) -> Vec<u8> {
+    match PublicKey::<C>::from_point(*point) {
+        Ok(point) => point.to_sec1_bytes(compressed),
+        Err(_) => vec![0x00],
+    }
+}
+
+//The order n of the curve, i.e. the prime of the scalar field
+pub fn curve_order<C: Curve>() -> BigInt {
+    BigInt::from_bytes_be(Sign::Plus, &C::ORDER.encode_field_bytes())
+}
+
+//Converts a field element to a scalar of the curve, the value is reduced mod n first
+pub fn bigint_to_scalar<C: CurveArithmetic>(value: BigInt) -> C::Scalar {
+    let reduced_value = value.modpow(&BigInt::from(1u8), &curve_order::<C>());
+    let mut field_bytes = FieldBytes::<C>::default();
+    field_bytes.copy_from_slice(&pad_to_length_big_endian(&reduced_value, FieldBytesSize::<C>::USIZE));
+    C::Scalar::from_repr(field_bytes).unwrap()
+}
+
+pub fn scalar_to_bigint<C: CurveArithmetic>(scalar: &C::Scalar) -> BigInt {
+    BigInt::from_bytes_be(Sign::Plus, &scalar.to_repr())
+}
+
+//Computes the sum of scalar_i * point_i using Pippenger's bucket method, which is much faster than one scalar multiplication per term
+//The scalars must be in [0, n-1]
+pub fn multi_scalar_mul<C: CurveArithmetic>(terms: &[(BigInt, C::ProjectivePoint)]) -> C::ProjectivePoint {
+    //The window is about log2(number of terms) - 2 bits, such that filling the buckets and summing them cost about the same
+    let window = (usize::BITS - terms.len().leading_zeros()).saturating_sub(2).clamp(2, 16) as usize;
+    let window_count = (curve_order::<C>().bits() as usize).div_ceil(window);
+    let digits: Vec<Vec<u64>> = terms.iter().map(|(scalar, _)| scalar.to_u64_digits().1).collect();
+
+    let mut result = C::ProjectivePoint::identity();
+    for window_index in (0..window_count).rev() {
+        for _ in 0..window {
+            result = result.double();
+        }
+        //Bucket d - 1 holds the sum of the points whose scalar has the digit d in this window
+        let mut buckets = vec![C::ProjectivePoint::identity(); (1 << window) - 1];
+        for (scalar_digits, (_, point)) in digits.iter().zip(terms) {
+            let digit = window_digit(scalar_digits, window_index * window, window);
+            if digit > 0 {
+                buckets[digit - 1] += point;
+            }
+        }
+        //Summing the running sums gives sum_d d * bucket_d
+        let mut running_sum = C::ProjectivePoint::identity();
+        let mut window_sum = C::ProjectivePoint::identity();
+        for bucket in buckets.iter().rev() {
+            running_sum += bucket;
+            window_sum += running_sum;
+        }
+        result += window_sum;
+    }
     result
 }
+
+//The bits start..start + width of a number given by its little endian 64 bit digits
+fn window_digit(digits: &[u64], start: usize, width: usize) -> usize {
+    (0..width).filter(|bit| {
+        let position = start + bit;
+        digits.get(position / 64).is_some_and(|digit| (digit >> (position % 64)) & 1 == 1)
+    }).map(|bit| 1 << bit).sum()
+}
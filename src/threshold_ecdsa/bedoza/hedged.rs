@@ -0,0 +1,54 @@
+use num_bigint::{BigInt, Sign};
+use rand::RngCore;
+use rfc6979::HmacDrbg;
+use sha2::{Digest, Sha256};
+use crate::threshold_ecdsa::bedoza::zp_field::{ZpField, ZpFieldElement};
+
+/*
+    Hedged generation of random values in the style of RFC 6979 (section 3.6), such that a party with a weak random number generator does not endanger the nonces.
+    HMAC_DRBG is seeded with the party's share x_i of a long-term key, the hash of a session context and fresh randomness as additional data,
+    i.e. the output is unpredictable as long as either the share is secret or the randomness is good, and the context keeps the outputs of two sessions apart.
+*/
+
+const FRESH_RANDOMNESS_LENGTH: usize = 32;
+
+pub struct HedgedGenerator {
+    drbg: HmacDrbg<Sha256>,
+    zp_field: ZpField,
+}
+
+impl HedgedGenerator {
+    //Seeds the generator with fresh randomness from the thread RNG, without a key share the output is only as good as this randomness
+    pub fn new(zp_field: &ZpField, key_share: Option<&ZpFieldElement>, context: &[u8]) -> Self {
+        let mut fresh_randomness = [0u8; FRESH_RANDOMNESS_LENGTH];
+        rand::thread_rng().fill_bytes(&mut fresh_randomness);
+        Self::from_randomness(zp_field, key_share, context, &fresh_randomness)
+    }
+
+    //The output is deterministic given the inputs, so the randomness should only be chosen by the caller for testing
+    pub fn from_randomness(zp_field: &ZpField, key_share: Option<&ZpFieldElement>, context: &[u8], fresh_randomness: &[u8]) -> Self {
+        let length = zp_field.p.bits().div_ceil(8) as usize;
+        let key_bytes = key_share.map_or(vec![], |share| {
+            let bytes = share.to_bytes_be().1;
+            [vec![0; length.saturating_sub(bytes.len())], bytes].concat()
+        });
+        Self {
+            drbg: HmacDrbg::new(&key_bytes, &Sha256::digest(context), fresh_randomness),
+            zp_field: zp_field.clone(),
+        }
+    }
+
+    //The next element in [1, p-1], candidates are the leftmost bits of the output and out of range candidates are discarded, as in RFC 6979
+    pub fn next_element(&mut self) -> ZpFieldElement {
+        let bits = self.zp_field.p.bits();
+        let length = bits.div_ceil(8) as usize;
+        let mut bytes = vec![0u8; length];
+        loop {
+            self.drbg.fill_bytes(&mut bytes);
+            let candidate = BigInt::from_bytes_be(Sign::Plus, &bytes) >> (8 * length as u64 - bits);
+            if candidate > BigInt::from(0) && candidate < self.zp_field.p {
+                return candidate;
+            }
+        }
+    }
+}
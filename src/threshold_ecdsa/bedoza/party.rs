@@ -1,22 +1,34 @@
 use crate::threshold_ecdsa::ot::{elgamal::Group, Chooser, Producer};
 use crate::threshold_ecdsa::bedoza::zp_field::{ZpField, ZpFieldElement};
 use num_bigint::BigInt;
-use p256::ProjectivePoint;
+use elliptic_curve::group::Group as _;
+use elliptic_curve::CurveArithmetic;
+use p256::NistP256;
 use std::collections::HashMap;
-use crate::threshold_ecdsa::bedoza::ec_helpers::bigint_to_scalar;
-use super::ec_helpers;
+use crate::threshold_ecdsa::bedoza::ec_helpers::{bigint_to_scalar, encode_point};
+use super::shamir;
+use super::hedged::HedgedGenerator;
+use super::SharingMode;
+use crate::threshold_ecdsa::policy::{PolicyViolation, SigningPolicy, SigningRequest};
+use std::time::SystemTime;
+use crate::threshold_ecdsa::bedoza::backup::{self, BackupError, ShareBackup};
+use crate::threshold_ecdsa::curve::SupportedCurve;
+use crate::threshold_ecdsa::dkg::{self, DkgReveal};
+use crate::threshold_ecdsa::public_key::PublicKey;
 
 pub type ShareName = String;
 
-pub struct Party {
+//A party holding shares of values in Zp, and of points on the elliptic curve C (P-256 by default)
+pub struct Party<C: CurveArithmetic = NistP256> {
     _ot_producer: Producer,
     _ot_chooser: Chooser,
     zp_shares: HashMap<String, BigInt>,
-    ec_shares: HashMap<String, ProjectivePoint>,
+    ec_shares: HashMap<String, C::ProjectivePoint>,
     zp_field: ZpField,
+    signing_policy: SigningPolicy,
 }
 
-impl Party {
+impl<C: CurveArithmetic> Party<C> {
     pub fn new(common_group: Group, zp_field: ZpField) -> Self {
         Self {
             _ot_producer: Producer::new(common_group.clone(), 2,
@@ -26,35 +38,148 @@ impl Party {
             _ot_chooser: Chooser::new(common_group, 2),
             zp_shares: HashMap::new(),
             ec_shares: HashMap::new(),
-            zp_field: zp_field,
+            zp_field,
+            signing_policy: SigningPolicy::new(),
         }
     }
 
-    //Generates a random share, thus two of these shares can be used to create a secret sharing of a random value
+    //Generates a random share, thus one of these shares from every party can be used to create a secret sharing of a random value
     pub fn rand(&mut self, name_of_new_share: ShareName) {
         let random_element: ZpFieldElement = self.zp_field.generate_random_element();
         self.zp_shares.insert(name_of_new_share, random_element);
     }
 
-    //Creates a new secret sharing of a value, keeps one of the shares and returns the other
-    pub fn create_secret_share(&mut self, name_of_new_share: ShareName, value: ZpFieldElement) -> ZpFieldElement {
-        let random_element: ZpFieldElement = self.zp_field.generate_random_element();
-        let own_share = self.zp_field.add(value, -random_element.clone()); //Thus own_share + random_element = value
+    //Generates a hedged random share, derived from the party's share of the hedging key, fresh randomness and the context (see hedged.rs)
+    pub fn hedged_rand(&mut self, name_of_new_share: ShareName, hedging_key: Option<&str>, context: &[u8]) {
+        let random_element = self.hedged_generator(hedging_key, context).next_element();
+        self.zp_shares.insert(name_of_new_share, random_element);
+    }
+
+    //Creates Shamir shares of a hedged random value, the polynomial is taken from the same generator as the value
+    pub fn create_hedged_shamir_shares(&self, hedging_key: Option<&str>, context: &[u8], degree: usize, party_indices: &[usize]) -> Vec<ZpFieldElement> {
+        let mut generator = self.hedged_generator(hedging_key, context);
+        let random_element = generator.next_element();
+        shamir::create_shares_with_randomness(&self.zp_field, random_element, degree, party_indices, || generator.next_element())
+    }
+
+    fn hedged_generator(&self, hedging_key: Option<&str>, context: &[u8]) -> HedgedGenerator {
+        let key_share = hedging_key.map(|key| self.zp_shares.get(key).expect("The party holds no share of the hedging key"));
+        HedgedGenerator::new(&self.zp_field, key_share, context)
+    }
+
+    //Creates a new secret sharing of a value, keeps one of the shares and returns the shares for the other_party_count other parties
+    pub fn create_secret_shares(&mut self, name_of_new_share: ShareName, value: ZpFieldElement, other_party_count: usize) -> Vec<ZpFieldElement> {
+        let random_elements: Vec<ZpFieldElement> = (0..other_party_count).map(|_| self.zp_field.generate_random_element()).collect();
+        let own_share = random_elements.iter().fold(value, |acc, random_element| self.zp_field.add(acc, -random_element.clone())); //Thus own_share + sum(random_elements) = value
         self.zp_shares.insert(name_of_new_share, own_share);
-        random_element
+        random_elements
+    }
+
+    //Creates Shamir shares of a value using a random polynomial of the given degree, one share for each of the given parties (possibly including itself)
+    pub fn create_shamir_shares(&self, value: ZpFieldElement, degree: usize, party_indices: &[usize]) -> Vec<ZpFieldElement> {
+        shamir::create_shares(&self.zp_field, value, degree, party_indices)
+    }
+
+    //Creates Shamir shares of a random value, used when all parties contribute to a random shared value
+    pub fn create_random_shamir_shares(&self, degree: usize, party_indices: &[usize]) -> Vec<ZpFieldElement> {
+        let random_element: ZpFieldElement = self.zp_field.generate_random_element();
+        self.create_shamir_shares(random_element, degree, party_indices)
+    }
+
+    //Multiplies two shares locally and creates Shamir shares of the product, used for degree reduction after multiplication
+    pub fn reshare_product(&self, input_share1: ShareName, input_share2: ShareName, degree: usize, party_indices: &[usize]) -> Vec<ZpFieldElement> {
+        let product = self.zp_field.mul(self.open_share(input_share1), self.open_share(input_share2)); //This is a share of a polynomial of degree 2t
+        self.create_shamir_shares(product, degree, party_indices)
+    }
+
+    //Creates shares of zero for all party_count parties, used to re-randomize the shares of a value without changing it
+    pub fn create_zero_shares(&self, sharing_mode: SharingMode, party_indices: &[usize]) -> Vec<ZpFieldElement> {
+        let zero = BigInt::from(0);
+        match sharing_mode {
+            SharingMode::Additive => {
+                let mut shares: Vec<ZpFieldElement> = (1..party_indices.len()).map(|_| self.zp_field.generate_random_element()).collect();
+                let sum_of_random_shares = shares.iter().fold(zero, |acc, share| self.zp_field.add(acc, share.clone()));
+                shares.push(self.zp_field.create_field_element(-sum_of_random_shares)); //Thus all shares sum to zero
+                shares
+            }
+            SharingMode::Shamir { threshold } => self.create_shamir_shares(zero, threshold, party_indices),
+        }
+    }
+
+    //Adds a share of zero to the share of a value (and its EC share if it exists), such that the old shares can no longer be combined with the new ones
+    pub fn refresh_share(&mut self, share: ShareName, zero_share: ZpFieldElement) {
+        let mut found = false;
+        if let Some(value) = self.zp_shares.get(&share) {
+            let new_value = self.zp_field.add(value.clone(), zero_share.clone());
+            self.zp_shares.insert(share.clone(), new_value);
+            found = true;
+        }
+        if let Some(point) = self.ec_shares.get(&share) {
+            let new_point = *point + C::ProjectivePoint::generator() * bigint_to_scalar::<C>(zero_share);
+            self.ec_shares.insert(share, new_point);
+            found = true;
+        }
+        if !found {
+            panic!("Share to refresh not found")
+        }
+    }
+
+    //Erases the share of a value (and its EC share if it exists), such that the value can no longer be used or opened
+    pub fn delete_share(&mut self, share: &ShareName) {
+        self.zp_shares.remove(share);
+        self.ec_shares.remove(share);
+    }
+
+    //Whether the party holds a Zp share or an EC share of the value
+    pub fn holds_share(&self, share: &str) -> bool {
+        self.zp_shares.contains_key(share) || self.ec_shares.contains_key(share)
     }
 
-    //Receives and saves a secret share from the other party
+    pub fn holds_ec_share(&self, share: &str) -> bool {
+        self.ec_shares.contains_key(share)
+    }
+
+    //The number of values the party holds a Zp share of
+    pub fn share_count(&self) -> usize {
+        self.zp_shares.len()
+    }
+
+    //Receives and saves a secret share from another party
     pub fn receive_secret_share(&mut self, name_of_new_share: ShareName, value: ZpFieldElement) {
         self.zp_shares.insert(name_of_new_share, value);
     }
 
+    //Receives a part of a secret share from another party and adds it to the part already received (if any)
+    pub fn receive_secret_share_part(&mut self, name_of_share: ShareName, value: ZpFieldElement) {
+        let current_value = self.zp_shares.get(&name_of_share).cloned().unwrap_or(BigInt::from(0));
+        let new_value = self.zp_field.add(current_value, value);
+        self.zp_shares.insert(name_of_share, new_value);
+    }
+
+    pub fn signing_policy(&self) -> &SigningPolicy {
+        &self.signing_policy
+    }
+
+    pub fn set_signing_policy(&mut self, signing_policy: SigningPolicy) {
+        self.signing_policy = signing_policy;
+    }
+
+    //Checks whether the party is willing to release its share of a signature
+    pub fn evaluate_signing_request(&self, request: &SigningRequest, now: SystemTime) -> Result<(), PolicyViolation> {
+        self.signing_policy.evaluate(request, now)
+    }
+
+    //Records a signature the party has released its share for, such that it counts towards the limits of the policy
+    pub fn record_signature(&mut self, request: &SigningRequest, now: SystemTime) {
+        self.signing_policy.record(request, now);
+    }
+
     //Opens a share, returns the value of the share
     pub fn open_share(&self, share_to_open: ShareName) -> ZpFieldElement {
         let value = self.zp_shares.get(&share_to_open);
         match value {
             Some(v) => {
-                return v.clone()
+                v.clone()
             }
             None => {
                 panic!("Share not found")
@@ -108,12 +233,17 @@ impl Party {
 
     //Converts an already shared value in Zp to an EC share
     pub fn convert_to_ec_shares(&mut self, share: ShareName) {
-        let maybe_value = self.zp_shares.get_key_value(&share);
+        self.mul_point_by_share(share.clone(), share, C::ProjectivePoint::generator());
+    }
+
+    //Multiplies a point with the share of a value in Zp, i.e. the share a_i gives the EC share a_i * P
+    //With P = G this is the conversion above, with another point it is used for Diffie-Hellman
+    pub fn mul_point_by_share(&mut self, input_share: ShareName, output_share: ShareName, point: C::ProjectivePoint) {
+        let maybe_value = self.zp_shares.get_key_value(&input_share);
         match maybe_value {
             Some((_, value)) => {
-                let scalar = ec_helpers::bigint_to_scalar(value.clone());
-                let point = ProjectivePoint::GENERATOR * scalar;
-                self.ec_shares.insert(share, point);
+                let scalar = bigint_to_scalar::<C>(value.clone());
+                self.ec_shares.insert(output_share, point * scalar);
             }
             None => {
                 panic!("Share of type Zp field element not found, make sure to create the share first, before converting it to an EC share")
@@ -123,11 +253,11 @@ impl Party {
     }
 
     //Opens an EC share, returns the value of the share
-    pub fn open_ec_share(&self, share: ShareName) -> ProjectivePoint {
+    pub fn open_ec_share(&self, share: ShareName) -> C::ProjectivePoint {
         let maybe_point = self.ec_shares.get_key_value(&share);
         match maybe_point {
             Some((_, point)) => {
-                return point.clone()
+                *point
             }
             None => {
                 panic!("Share of type EC point not found, make sure to create the share first, before opening it, i.e. using convert_to_ec_shares()")
@@ -140,13 +270,57 @@ impl Party {
         let maybe_share = self.ec_shares.get_key_value(&input_share);
         match maybe_share {
             Some((_, v)) => {
-                let scalar = bigint_to_scalar(constant);
-                let new_value = v * &scalar;
-                self.ec_shares.insert(output_share, new_value.clone());
+                let scalar = bigint_to_scalar::<C>(constant);
+                let new_value = *v * scalar;
+                self.ec_shares.insert(output_share, new_value);
             }
             None => {
                 panic!("Input Share not found")
             }
         }
     }
-}
\ No newline at end of file
+}
+
+impl<C: SupportedCurve> Party<C> {
+    //Reveals the EC share of a value together with a proof of knowledge of the share, for the key generation in dkg.rs
+    pub fn prove_ec_share(&self, party_index: usize, share: ShareName, session_id: &[u8]) -> DkgReveal<C> {
+        let ec_share = self.open_ec_share(share.clone());
+        let proof = dkg::prove_knowledge::<C>(&self.zp_field, party_index, self.open_share(share), &ec_share, session_id);
+        DkgReveal { party: party_index, ec_share, proof }
+    }
+
+    //Exports the share of a value (e.g. [sk_j]) as a backup encrypted under the passphrase
+    //The backup carries the EC share and the public key, such that a restored share can be checked
+    pub fn export_share_backup(&self, party_index: usize, share: &ShareName, public_key: &PublicKey<C>, passphrase: &str) -> Vec<u8> {
+        let value = self.open_share(share.clone());
+        let share_backup = ShareBackup {
+            party_index,
+            share_name: share.clone(),
+            share: value.clone(),
+            ec_share: encode_point::<C>(&(C::ProjectivePoint::generator() * bigint_to_scalar::<C>(value)), false),
+            public_key: public_key.to_sec1_bytes(false),
+        };
+        backup::encrypt_backup(&share_backup, passphrase, backup::DEFAULT_PBKDF2_ITERATIONS)
+    }
+
+    //Reads a share from a backup, the EC share is recomputed from the share and checked against the EC share in the backup
+    //The shares of the party are not changed, the share is only stored by store_restored_share once it has been checked against the public key
+    pub fn import_share_backup(&self, party_index: usize, encrypted: &[u8], passphrase: &str) -> Result<ShareBackup, BackupError> {
+        let share_backup = backup::decrypt_backup(encrypted, passphrase)?;
+        if share_backup.party_index != party_index {
+            return Err(BackupError::WrongParty { expected: party_index, actual: share_backup.party_index });
+        }
+        let ec_share = C::ProjectivePoint::generator() * bigint_to_scalar::<C>(share_backup.share.clone());
+        if share_backup.share.sign() == num_bigint::Sign::Minus || share_backup.share >= self.zp_field.p || encode_point::<C>(&ec_share, false) != share_backup.ec_share {
+            return Err(BackupError::InconsistentShare);
+        }
+        Ok(share_backup)
+    }
+
+    //Stores a share read from a backup together with its EC share, replacing the share of the same name if the party still holds one
+    pub fn store_restored_share(&mut self, share_backup: &ShareBackup) {
+        let ec_share = C::ProjectivePoint::generator() * bigint_to_scalar::<C>(share_backup.share.clone());
+        self.zp_shares.insert(share_backup.share_name.clone(), share_backup.share.clone());
+        self.ec_shares.insert(share_backup.share_name.clone(), ec_share);
+    }
+}
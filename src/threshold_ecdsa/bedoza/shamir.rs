@@ -0,0 +1,46 @@
+use num_bigint::BigInt;
+use super::zp_field::{ZpField, ZpFieldElement};
+
+/*
+    Helper functions for Shamir secret sharing over Zp.
+    The party with index i holds the evaluation of the polynomial in the point i + 1, such that the secret is the evaluation in 0.
+*/
+
+//The point in which the polynomial is evaluated to create the share of the party with the given index
+pub fn evaluation_point(party_index: usize) -> ZpFieldElement {
+    BigInt::from(party_index + 1)
+}
+
+//Creates shares of a value using a random polynomial of the given degree, one share for each of the given parties
+pub fn create_shares(zp_field: &ZpField, value: ZpFieldElement, degree: usize, party_indices: &[usize]) -> Vec<ZpFieldElement> {
+    create_shares_with_randomness(zp_field, value, degree, party_indices, || zp_field.generate_random_element())
+}
+
+//Creates shares of a value, where the other coefficients of the polynomial are taken from the given source of random elements
+pub fn create_shares_with_randomness<F: FnMut() -> ZpFieldElement>(zp_field: &ZpField, value: ZpFieldElement, degree: usize, party_indices: &[usize], mut random_element: F) -> Vec<ZpFieldElement> {
+    let mut coefficients = vec![value];
+    for _ in 0..degree {
+        coefficients.push(random_element());
+    }
+    party_indices.iter().map(|index| evaluate_polynomial(zp_field, &coefficients, evaluation_point(*index))).collect()
+}
+
+//Evaluates a polynomial given by its coefficients (lowest degree first) in the point x, using Horner's method
+fn evaluate_polynomial(zp_field: &ZpField, coefficients: &[ZpFieldElement], x: ZpFieldElement) -> ZpFieldElement {
+    coefficients.iter().rev().fold(BigInt::from(0), |acc, coefficient| {
+        zp_field.add(zp_field.mul(acc, x.clone()), coefficient.clone())
+    })
+}
+
+//Computes the Lagrange coefficients for interpolating the polynomial in 0 from the shares of the given parties
+pub fn lagrange_coefficients(zp_field: &ZpField, party_indices: &[usize]) -> Vec<ZpFieldElement> {
+    party_indices.iter().map(|i| {
+        let x_i = evaluation_point(*i);
+        party_indices.iter().filter(|j| *j != i).fold(BigInt::from(1), |acc, j| {
+            let x_j = evaluation_point(*j);
+            //The factor is (0 - x_j) / (x_i - x_j)
+            let denominator = zp_field.find_inverse(zp_field.add(x_i.clone(), -x_j.clone()));
+            zp_field.mul(acc, zp_field.mul(zp_field.create_field_element(-x_j), denominator))
+        })
+    }).collect()
+}
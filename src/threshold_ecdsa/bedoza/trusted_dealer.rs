@@ -1,10 +1,12 @@
 use super::zp_field::{ZpField, ZpFieldElement};
+use super::shamir;
+use super::SharingMode;
 
-//A triple of pairs containing secret sharings of u,v,w such that u*v = w
+//A triple of vectors containing secret sharings of u,v,w such that u*v = w, index i holds the shares of party i
 pub struct UvwTriple {
-    pub u: (ZpFieldElement, ZpFieldElement),
-    pub v: (ZpFieldElement, ZpFieldElement),
-    pub w: (ZpFieldElement, ZpFieldElement)
+    pub u: Vec<ZpFieldElement>,
+    pub v: Vec<ZpFieldElement>,
+    pub w: Vec<ZpFieldElement>
 }
 
 pub struct TrustedDealer {
@@ -16,26 +18,34 @@ impl TrustedDealer {
         Self { zp_field }
     }
 
-    //returns 3 tuples containing secret sharings of u,v,w such that u*v = w
-    pub fn generate_uvw(&self) -> UvwTriple {
+    //returns 3 vectors containing secret sharings of u,v,w between party_count parties such that u*v = w
+    pub fn generate_uvw(&self, sharing_mode: SharingMode, party_count: usize) -> UvwTriple {
         let u_value = self.zp_field.generate_random_element();
         let v_value = self.zp_field.generate_random_element();
         let w_value = self.zp_field.mul(u_value.clone(), v_value.clone());
-        
-        //Creating the shares
-        let u_alice_value = self.zp_field.generate_random_element();
-        let u_bob_value = self.zp_field.add(u_value.clone(), -u_alice_value.clone());
-
-        let v_alice_value = self.zp_field.generate_random_element();
-        let v_bob_value = self.zp_field.add(v_value.clone(), -v_alice_value.clone());
-
-        let w_alice_value = self.zp_field.generate_random_element();
-        let w_bob_value = self.zp_field.add(w_value.clone(), -w_alice_value.clone());
 
         UvwTriple{
-            u: (u_alice_value, u_bob_value),
-            v: (v_alice_value, v_bob_value),
-            w :(w_alice_value, w_bob_value)
+            u: self.create_shares(sharing_mode, u_value, party_count),
+            v: self.create_shares(sharing_mode, v_value, party_count),
+            w: self.create_shares(sharing_mode, w_value, party_count)
+        }
+    }
+
+    fn create_shares(&self, sharing_mode: SharingMode, value: ZpFieldElement, party_count: usize) -> Vec<ZpFieldElement> {
+        match sharing_mode {
+            SharingMode::Additive => self.create_additive_shares(value, party_count),
+            SharingMode::Shamir { threshold } => {
+                let all_parties: Vec<usize> = (0..party_count).collect();
+                shamir::create_shares(&self.zp_field, value, threshold, &all_parties)
+            }
         }
     }
-}
\ No newline at end of file
+
+    //Splits a value into party_count random shares which sum to the value
+    fn create_additive_shares(&self, value: ZpFieldElement, party_count: usize) -> Vec<ZpFieldElement> {
+        let mut shares: Vec<ZpFieldElement> = (1..party_count).map(|_| self.zp_field.generate_random_element()).collect();
+        let sum_of_random_shares = shares.iter().fold(self.zp_field.create_field_element(0.into()), |acc, share| self.zp_field.add(acc, share.clone()));
+        shares.push(self.zp_field.add(value, -sum_of_random_shares)); //Thus all shares sum to the value
+        shares
+    }
+}
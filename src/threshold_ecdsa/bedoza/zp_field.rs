@@ -3,6 +3,8 @@ use std::{fs::File, io::Write};
 use rand::prelude::Distribution;
 use num_bigint::{BigInt, BigUint, RandomBits, ToBigInt};
 use crate::threshold_ecdsa::prime_functions::generate_prime;
+use crate::threshold_ecdsa::bedoza::ec_helpers::curve_order;
+use elliptic_curve::Curve;
 
 pub type ZpFieldElement = BigInt;
 
@@ -27,6 +29,15 @@ impl ZpField {
         }
     }
 
+    //Creates the scalar field of an elliptic curve, i.e. Zn where n is the order of the curve
+    pub fn from_curve_order<C: Curve>() -> Self {
+        let order = curve_order::<C>();
+        Self {
+            size_in_bits: order.bits(),
+            p: order,
+        }
+    }
+
     //Creates a struct from a file to avoid prime re-generation
     pub fn struct_from_file(path: &str) -> Self {
         let file = File::open(path).unwrap();
@@ -91,8 +102,7 @@ pub fn gen_fixed_elliptical_curve_order_to_file(file_name: &str) {
     full_file_name.push_str(&file_type);
 
     //Order of the curve in the p256 elliptical curve in hex is 0xffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551
-    let order = BigInt::parse_bytes(b"ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551", 16).unwrap();
-    let prime_field = ZpField::new_from_prime(order.clone(), 256);
+    let prime_field = ZpField::from_curve_order::<p256::NistP256>();
     prime_field.struct_to_file(full_file_name.as_str());
 }
 
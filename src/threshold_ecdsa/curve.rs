@@ -0,0 +1,49 @@
+use elliptic_curve::pkcs8::{AssociatedOid, DecodePublicKey, EncodePublicKey, LineEnding};
+use elliptic_curve::sec1::{FromEncodedPoint, ModulusSize, ToEncodedPoint};
+use elliptic_curve::{AffinePoint, CurveArithmetic, FieldBytesSize, PrimeCurve, PublicKey};
+
+/*
+    The curves threshold ECDSA can be used with, i.e. prime order curves with arithmetic, SEC1 point encoding
+    and an OID for SubjectPublicKeyInfo encoding.
+    This is implemented for every such RustCrypto curve (e.g. NistP256, NistP384 and Secp256k1),
+    and bundles the encoding functions such that the rest of the code only needs the bound C: SupportedCurve.
+*/
+pub trait SupportedCurve: PrimeCurve + CurveArithmetic + AssociatedOid {
+    fn public_key_to_sec1_bytes(key: &PublicKey<Self>, compressed: bool) -> Vec<u8>;
+    fn public_key_from_sec1_bytes(bytes: &[u8]) -> Option<PublicKey<Self>>;
+    fn public_key_to_der(key: &PublicKey<Self>) -> Vec<u8>;
+    fn public_key_from_der(der: &[u8]) -> Option<PublicKey<Self>>;
+    fn public_key_to_pem(key: &PublicKey<Self>) -> String;
+    fn public_key_from_pem(pem: &str) -> Option<PublicKey<Self>>;
+}
+
+impl<C> SupportedCurve for C
+where
+    C: PrimeCurve + CurveArithmetic + AssociatedOid,
+    AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+    FieldBytesSize<C>: ModulusSize,
+{
+    fn public_key_to_sec1_bytes(key: &PublicKey<Self>, compressed: bool) -> Vec<u8> {
+        key.to_encoded_point(compressed).as_bytes().to_vec()
+    }
+
+    fn public_key_from_sec1_bytes(bytes: &[u8]) -> Option<PublicKey<Self>> {
+        PublicKey::from_sec1_bytes(bytes).ok()
+    }
+
+    fn public_key_to_der(key: &PublicKey<Self>) -> Vec<u8> {
+        key.to_public_key_der().unwrap().as_bytes().to_vec()
+    }
+
+    fn public_key_from_der(der: &[u8]) -> Option<PublicKey<Self>> {
+        PublicKey::from_public_key_der(der).ok()
+    }
+
+    fn public_key_to_pem(key: &PublicKey<Self>) -> String {
+        key.to_public_key_pem(LineEnding::LF).unwrap()
+    }
+
+    fn public_key_from_pem(pem: &str) -> Option<PublicKey<Self>> {
+        PublicKey::from_public_key_pem(pem).ok()
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+use elliptic_curve::group::Group as _;
+use num_bigint::{BigInt, Sign};
+use p256::NistP256;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use crate::threshold_ecdsa::bedoza::ec_helpers::{bigint_to_scalar, encode_point};
+use crate::threshold_ecdsa::bedoza::party::ShareName;
+use crate::threshold_ecdsa::bedoza::zp_field::{ZpField, ZpFieldElement};
+use crate::threshold_ecdsa::curve::SupportedCurve;
+use crate::threshold_ecdsa::public_key::PublicKey;
+use crate::threshold_ecdsa::ThresholdECDSA;
+
+/*
+    Commit-then-reveal distributed key generation, such that no party can choose its EC share after seeing the shares of the others.
+    Round 1: every party P_i picks its share x_i of [sk], computes X_i = x_i*G and a Schnorr proof of knowledge (R_i, z_i) of x_i,
+             where z_i = r_i + c_i*x_i and c_i = H(session || i || X_i || R_i), and broadcasts the commitment H(session || i || X_i || R_i || z_i).
+    Round 2: once all commitments have been received, every party reveals (X_i, R_i, z_i), and the reveals are checked against the commitments and proofs.
+    The reveals are held back in dkg_reveals until dkg_reveal has seen a commitment from every active party, and each reveal is handed out only once.
+    The public key is then pk = sum_i w_i*X_i, where w_i are the weights used for opening (1 for additive sharing, Lagrange coefficients for Shamir sharing).
+    The proof binds the session and the index of the party, so a party cannot copy (or negate) the share of another party without knowing its discrete logarithm.
+*/
+
+const SESSION_ID_LENGTH: usize = 32;
+
+//The commitment a party broadcasts in round 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DkgCommitment {
+    pub party: usize,
+    pub hash: [u8; 32],
+}
+
+//A Schnorr proof of knowledge of x such that X = x*G
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofOfKnowledge<C: SupportedCurve = NistP256> {
+    pub r: C::ProjectivePoint,
+    pub z: ZpFieldElement,
+}
+
+//The opening of a commitment a party broadcasts in round 2
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DkgReveal<C: SupportedCurve = NistP256> {
+    pub party: usize,
+    pub ec_share: C::ProjectivePoint,
+    pub proof: ProofOfKnowledge<C>,
+}
+
+//The state of a key generation after round 1, the reveals are held back by the parties until all commitments are received
+#[derive(Debug, Clone)]
+pub struct DkgSession {
+    pub sk: ShareName,
+    pub session_id: [u8; SESSION_ID_LENGTH],
+    pub commitments: Vec<DkgCommitment>,
+}
+
+//The reasons a key generation is aborted, each naming the party at fault
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DkgError {
+    //The party did not commit in round 1
+    MissingCommitment(usize),
+    //The party did not reveal in round 2
+    MissingReveal(usize),
+    //The reveal of the party does not match its commitment
+    CommitmentMismatch(usize),
+    //The proof of knowledge of the party does not verify
+    InvalidProof(usize),
+    //The reveals of the session have already been handed out, or the session is unknown
+    AlreadyRevealed,
+    //The shares of the parties combine to the identity point, which is not a valid public key
+    IdentityPublicKey,
+}
+
+impl fmt::Display for DkgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DkgError::MissingCommitment(party) => write!(f, "party {} did not commit to its share", party),
+            DkgError::MissingReveal(party) => write!(f, "party {} did not reveal its share", party),
+            DkgError::CommitmentMismatch(party) => write!(f, "the share revealed by party {} does not match its commitment", party),
+            DkgError::InvalidProof(party) => write!(f, "the proof of knowledge of party {} is invalid", party),
+            DkgError::AlreadyRevealed => write!(f, "the reveals of the key generation have already been handed out"),
+            DkgError::IdentityPublicKey => write!(f, "the generated public key is the identity point"),
+        }
+    }
+}
+
+impl std::error::Error for DkgError {}
+
+//Proves knowledge of x such that ec_share = x*G, for the party with the given index in the given session
+pub fn prove_knowledge<C: SupportedCurve>(zp_field: &ZpField, party: usize, x: ZpFieldElement, ec_share: &C::ProjectivePoint, session_id: &[u8]) -> ProofOfKnowledge<C> {
+    let mut nonce = zp_field.generate_random_element();
+    while nonce == BigInt::from(0) {
+        nonce = zp_field.generate_random_element();
+    }
+    let r = C::ProjectivePoint::generator() * bigint_to_scalar::<C>(nonce.clone());
+    let c = proof_challenge::<C>(zp_field, party, ec_share, &r, session_id);
+    let z = zp_field.add(nonce, zp_field.mul(c, x));
+    ProofOfKnowledge { r, z }
+}
+
+//The commitment to a reveal, which a party broadcasts before the reveal itself
+pub fn commit<C: SupportedCurve>(zp_field: &ZpField, reveal: &DkgReveal<C>, session_id: &[u8]) -> DkgCommitment {
+    let mut hasher = Sha256::new();
+    hasher.update(b"threshold ecdsa dkg commitment");
+    hasher.update(session_id);
+    hasher.update((reveal.party as u32).to_be_bytes());
+    hasher.update(encode_point::<C>(&reveal.ec_share, true));
+    hasher.update(encode_point::<C>(&reveal.proof.r, true));
+    let scalar_length = zp_field.p.bits().div_ceil(8) as usize;
+    let z_bytes = reveal.proof.z.to_bytes_be().1;
+    hasher.update(vec![0; scalar_length.saturating_sub(z_bytes.len())]);
+    hasher.update(z_bytes);
+    DkgCommitment { party: reveal.party, hash: hasher.finalize().into() }
+}
+
+//Checks a reveal against the commitment of the same party, and checks its proof of knowledge
+pub fn check_reveal<C: SupportedCurve>(zp_field: &ZpField, commitment: &DkgCommitment, reveal: &DkgReveal<C>, session_id: &[u8]) -> Result<(), DkgError> {
+    if commitment.party != reveal.party || commit(zp_field, reveal, session_id) != *commitment {
+        return Err(DkgError::CommitmentMismatch(reveal.party));
+    }
+    let z = &reveal.proof.z;
+    if bool::from(reveal.ec_share.is_identity()) || z.sign() == Sign::Minus || *z >= zp_field.p {
+        return Err(DkgError::InvalidProof(reveal.party));
+    }
+    let c = proof_challenge::<C>(zp_field, reveal.party, &reveal.ec_share, &reveal.proof.r, session_id);
+    let left = C::ProjectivePoint::generator() * bigint_to_scalar::<C>(z.clone());
+    let right = reveal.proof.r + reveal.ec_share * bigint_to_scalar::<C>(c);
+    if left != right {
+        return Err(DkgError::InvalidProof(reveal.party));
+    }
+    Ok(())
+}
+
+impl<C: SupportedCurve> ThresholdECDSA<C> {
+    //Round 1 of the key generation, the active parties pick their shares of [sk] and commit to their EC shares
+    pub fn dkg_commit(&mut self) -> DkgSession {
+        let mut session_id = [0u8; SESSION_ID_LENGTH];
+        rand::thread_rng().fill_bytes(&mut session_id);
+        let sk = self.bedoza.rand();
+        self.bedoza.convert_ec(sk.clone());
+        let reveals: Vec<DkgReveal<C>> = self.bedoza.active_parties().iter().map(|index| {
+            self.bedoza.party(*index).prove_ec_share(*index, sk.clone(), &session_id)
+        }).collect();
+        let commitments = reveals.iter().map(|reveal| commit(&self.zp_field, reveal, &session_id)).collect();
+        self.dkg_reveals.insert(sk.clone(), reveals);
+        DkgSession { sk, session_id, commitments }
+    }
+
+    //Round 2 of the key generation, the parties hand out their reveals once the session holds a commitment from every active party
+    //The reveals of a session are only handed out once
+    pub fn dkg_reveal(&mut self, session: &DkgSession) -> Result<Vec<DkgReveal<C>>, DkgError> {
+        let reveals = self.dkg_reveals.remove(&session.sk).ok_or(DkgError::AlreadyRevealed)?;
+        if let Some(index) = self.bedoza.active_parties().iter().copied().find(|index| session.commitments.iter().all(|commitment| commitment.party != *index)) {
+            self.bedoza.delete(session.sk.clone());
+            return Err(DkgError::MissingCommitment(index));
+        }
+        Ok(reveals)
+    }
+
+    //Completes the key generation, the reveals are checked and combined to the public key
+    //If any check fails the parties erase their shares of [sk], and the party at fault is named in the error
+    pub fn dkg_finish(&mut self, session: DkgSession, reveals: &[DkgReveal<C>]) -> Result<(ShareName, PublicKey<C>), DkgError> {
+        let result = self.check_dkg_session(&session, reveals).and_then(|ec_shares| {
+            PublicKey::from_point(self.bedoza.combine_ec_shares(&ec_shares)).map_err(|_| DkgError::IdentityPublicKey)
+        });
+        match result {
+            Ok(pk) => {
+                self.public_keys.insert(session.sk.clone(), pk);
+                Ok((session.sk, pk))
+            }
+            Err(error) => {
+                self.bedoza.delete(session.sk);
+                Err(error)
+            }
+        }
+    }
+
+    //The revealed EC shares in the order of the active parties, if every active party committed and revealed correctly
+    fn check_dkg_session(&self, session: &DkgSession, reveals: &[DkgReveal<C>]) -> Result<Vec<C::ProjectivePoint>, DkgError> {
+        self.bedoza.active_parties().iter().map(|index| {
+            let commitment = session.commitments.iter().find(|commitment| commitment.party == *index).ok_or(DkgError::MissingCommitment(*index))?;
+            let reveal = reveals.iter().find(|reveal| reveal.party == *index).ok_or(DkgError::MissingReveal(*index))?;
+            check_reveal(&self.zp_field, commitment, reveal, &session.session_id)?;
+            Ok(reveal.ec_share)
+        }).collect()
+    }
+}
+
+//The challenge c = H(session || i || X_i || R_i) mod n
+fn proof_challenge<C: SupportedCurve>(zp_field: &ZpField, party: usize, ec_share: &C::ProjectivePoint, r: &C::ProjectivePoint, session_id: &[u8]) -> ZpFieldElement {
+    let mut hasher = Sha256::new();
+    hasher.update(b"threshold ecdsa dkg proof");
+    hasher.update(session_id);
+    hasher.update((party as u32).to_be_bytes());
+    hasher.update(encode_point::<C>(ec_share, true));
+    hasher.update(encode_point::<C>(r, true));
+    zp_field.create_field_element(BigInt::from_bytes_be(Sign::Plus, &hasher.finalize()))
+}
@@ -0,0 +1,100 @@
+use std::fmt;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use elliptic_curve::generic_array::typenum::Unsigned;
+use elliptic_curve::group::{Curve as _, Group as _};
+use elliptic_curve::point::AffineCoordinates;
+use elliptic_curve::FieldBytesSize;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use crate::threshold_ecdsa::bedoza::ec_helpers::bigint_to_scalar;
+use crate::threshold_ecdsa::bedoza::zp_field::ZpField;
+use crate::threshold_ecdsa::curve::SupportedCurve;
+use crate::threshold_ecdsa::public_key::PublicKey;
+
+/*
+    ECIES encryption to a (threshold) public key pk.
+    The sender picks an ephemeral key e and computes the shared point S = e*pk = sk*(e*G),
+    a key and a nonce for AES-256-GCM are then derived as HKDF-SHA256(x-coordinate of S, info = SEC1(e*G)).
+    The ciphertext is SEC1(e*G) (uncompressed) || AES-256-GCM(M) || tag.
+    Only encryption and the key derivation are done here, the parties compute sk*(e*G) using threshold ECDH.
+*/
+
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EciesError {
+    //The ciphertext is too short to contain an ephemeral key and a tag
+    Truncated,
+    //The ephemeral key is not a point on the curve
+    InvalidEphemeralKey,
+    //The ciphertext or tag has been tampered with, or it was encrypted to another key
+    DecryptionFailed,
+}
+
+impl fmt::Display for EciesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EciesError::Truncated => write!(f, "the ciphertext is too short"),
+            EciesError::InvalidEphemeralKey => write!(f, "the ephemeral key is not a valid point"),
+            EciesError::DecryptionFailed => write!(f, "the ciphertext could not be authenticated"),
+        }
+    }
+}
+
+impl std::error::Error for EciesError {}
+
+//The length of the uncompressed SEC1 encoding of the ephemeral key which starts every ciphertext
+pub fn ephemeral_key_length<C: SupportedCurve>() -> usize {
+    1 + 2 * FieldBytesSize::<C>::USIZE
+}
+
+//Encrypts a message to pk, this needs no interaction and can be done by anyone
+pub fn encrypt<C: SupportedCurve>(pk: &PublicKey<C>, plaintext: &[u8]) -> Vec<u8> {
+    let zp_field = ZpField::from_curve_order::<C>();
+    let mut ephemeral_secret = zp_field.generate_random_element();
+    while ephemeral_secret == 0.into() {
+        ephemeral_secret = zp_field.generate_random_element();
+    }
+    let ephemeral_secret = bigint_to_scalar::<C>(ephemeral_secret);
+    let ephemeral_key = PublicKey::<C>::from_point(C::ProjectivePoint::generator() * ephemeral_secret).unwrap();
+    let shared_point = pk.to_projective() * ephemeral_secret;
+
+    let mut ciphertext = ephemeral_key.to_sec1_bytes(false);
+    let (cipher, nonce) = derive_cipher::<C>(&shared_point, &ciphertext);
+    ciphertext.extend(cipher.encrypt(&nonce, plaintext).unwrap());
+    ciphertext
+}
+
+//Parses the ephemeral key e*G from the start of a ciphertext
+pub fn parse_ephemeral_key<C: SupportedCurve>(ciphertext: &[u8]) -> Result<PublicKey<C>, EciesError> {
+    let key_length = ephemeral_key_length::<C>();
+    if ciphertext.len() < key_length + 16 {
+        return Err(EciesError::Truncated);
+    }
+    PublicKey::from_sec1_bytes(&ciphertext[..key_length]).map_err(|_| EciesError::InvalidEphemeralKey)
+}
+
+//Decrypts a ciphertext given the shared point S = sk*(e*G)
+pub fn decrypt_with_shared_point<C: SupportedCurve>(shared_point: &C::ProjectivePoint, ciphertext: &[u8]) -> Result<Vec<u8>, EciesError> {
+    let key_length = ephemeral_key_length::<C>();
+    if ciphertext.len() < key_length + 16 {
+        return Err(EciesError::Truncated);
+    }
+    let (ephemeral_key, encrypted) = ciphertext.split_at(key_length);
+    let (cipher, nonce) = derive_cipher::<C>(shared_point, ephemeral_key);
+    cipher.decrypt(&nonce, encrypted).map_err(|_| EciesError::DecryptionFailed)
+}
+
+fn derive_cipher<C: SupportedCurve>(shared_point: &C::ProjectivePoint, ephemeral_key: &[u8]) -> (Aes256Gcm, Nonce<aes_gcm::aead::consts::U12>) {
+    //The shared point can not be the identity, since both the ephemeral key and the public key are valid points
+    debug_assert!(!bool::from(shared_point.is_identity()));
+    let shared_x = shared_point.to_affine().x();
+    let hkdf = Hkdf::<Sha256>::new(None, &shared_x);
+    let mut okm = [0u8; KEY_LENGTH + NONCE_LENGTH];
+    hkdf.expand(ephemeral_key, &mut okm).unwrap();
+    let cipher = Aes256Gcm::new_from_slice(&okm[..KEY_LENGTH]).unwrap();
+    let nonce: [u8; NONCE_LENGTH] = okm[KEY_LENGTH..].try_into().unwrap();
+    (cipher, Nonce::from(nonce))
+}
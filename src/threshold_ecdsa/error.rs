@@ -0,0 +1,192 @@
+use std::{fmt, io};
+use crate::threshold_ecdsa::audit::AuditError;
+use crate::threshold_ecdsa::bedoza::party::ShareName;
+use crate::threshold_ecdsa::hashing::InvalidDigestLength;
+use crate::threshold_ecdsa::jws::JwsError;
+use crate::threshold_ecdsa::keyring::UserId;
+use crate::threshold_ecdsa::policy::PolicyRefusal;
+use crate::threshold_ecdsa::signature::SignatureEncodingError;
+use crate::threshold_ecdsa::ssh::SshError;
+use crate::threshold_ecdsa::x509::CertificateError;
+
+//The reasons a signature can be refused by the parties
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SigningError {
+    //The nonce <k> of the presignature has already been used for a signature, using it again would leak sk
+    PresignatureAlreadyUsed(ShareName),
+    //The prehashed digest given to sign_digest has the wrong length
+    InvalidDigest(InvalidDigestLength),
+    //The message could not be read when signing from a stream
+    Io(String),
+    //The opened signature does not verify under the public key, e.g. because a party used a wrong share
+    InvalidSignature,
+    //The key to sign with could not be found in the keyring
+    Keyring(KeyringError),
+    //A party refused to release its share of s, since the signature violates its signing policy
+    PolicyRefused(PolicyRefusal),
+    //The opened nonce has the same r as an earlier signature, or the signature could not be recorded in the audit log
+    Audit(AuditError),
+    //The tuple [sk_j'] was not preprocessed using a known key (or its key has been deleted), so the signing policies cannot be evaluated
+    UnknownKey(ShareName),
+    //The token to sign is not an ES256 token
+    Jws(JwsError),
+    //The tuple [sk_j'] was not preprocessed using the key of the public key the signature is checked against
+    KeyMismatch(ShareName),
+    //The certificate or certification request to sign cannot be encoded
+    Certificate(CertificateError),
+    //The sshsig signature to produce is not valid, e.g. because its namespace is empty
+    Ssh(SshError),
+}
+
+impl fmt::Display for SigningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigningError::PresignatureAlreadyUsed(k) => write!(f, "the presignature with nonce {} has already been used", k),
+            SigningError::InvalidDigest(error) => write!(f, "invalid digest: {}", error),
+            SigningError::Io(error) => write!(f, "could not read the message: {}", error),
+            SigningError::InvalidSignature => write!(f, "the produced signature does not verify"),
+            SigningError::Keyring(error) => write!(f, "{}", error),
+            SigningError::PolicyRefused(refusal) => write!(f, "{}", refusal),
+            SigningError::Audit(error) => write!(f, "{}", error),
+            SigningError::UnknownKey(sk_j_prime) => write!(f, "the key of the preprocessed tuple {} is unknown", sk_j_prime),
+            SigningError::Jws(error) => write!(f, "{}", error),
+            SigningError::KeyMismatch(sk_j_prime) => write!(f, "the tuple {} was not preprocessed using the key of the public key", sk_j_prime),
+            SigningError::Certificate(error) => write!(f, "{}", error),
+            SigningError::Ssh(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for SigningError {}
+
+impl From<InvalidDigestLength> for SigningError {
+    fn from(error: InvalidDigestLength) -> Self {
+        SigningError::InvalidDigest(error)
+    }
+}
+
+impl From<io::Error> for SigningError {
+    fn from(error: io::Error) -> Self {
+        SigningError::Io(error.to_string())
+    }
+}
+
+impl From<PolicyRefusal> for SigningError {
+    fn from(refusal: PolicyRefusal) -> Self {
+        SigningError::PolicyRefused(refusal)
+    }
+}
+
+impl From<AuditError> for SigningError {
+    fn from(error: AuditError) -> Self {
+        SigningError::Audit(error)
+    }
+}
+
+impl From<JwsError> for SigningError {
+    fn from(error: JwsError) -> Self {
+        SigningError::Jws(error)
+    }
+}
+
+impl From<CertificateError> for SigningError {
+    fn from(error: CertificateError) -> Self {
+        SigningError::Certificate(error)
+    }
+}
+
+impl From<SshError> for SigningError {
+    fn from(error: SshError) -> Self {
+        SigningError::Ssh(error)
+    }
+}
+
+impl From<KeyringError> for SigningError {
+    fn from(error: KeyringError) -> Self {
+        SigningError::Keyring(error)
+    }
+}
+
+//The reasons a keyring operation can fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyringError {
+    //The user has no key in the keyring
+    UnknownUser(UserId),
+    //The user already has a key in the keyring
+    DuplicateUser(UserId),
+}
+
+impl fmt::Display for KeyringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyringError::UnknownUser(user_id) => write!(f, "the user {} has no key", user_id),
+            KeyringError::DuplicateUser(user_id) => write!(f, "the user {} already has a key", user_id),
+        }
+    }
+}
+
+impl std::error::Error for KeyringError {}
+
+//The reasons the shares of a value cannot be refreshed, the shares are left unchanged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefreshError {
+    //No party holds a share of the value, e.g. since it has been deleted
+    UnknownShare(ShareName),
+    //The party holds a share of the value but is not active, so it would be left with its old share
+    InactiveParty { share: ShareName, party: usize },
+}
+
+impl fmt::Display for RefreshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RefreshError::UnknownShare(share) => write!(f, "no party holds a share of {}", share),
+            RefreshError::InactiveParty { share, party } => write!(f, "party {} holds a share of {} but is not active", party, share),
+        }
+    }
+}
+
+impl std::error::Error for RefreshError {}
+
+//The reasons a signature can be rejected by the verifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationError {
+    //r or s is not in the range [1, n-1]
+    ScalarOutOfRange,
+    //The point computed from the signature is the identity point, which has no x-coordinate
+    IdentityPoint,
+    //The signature could not be parsed
+    InvalidEncoding(SignatureEncodingError),
+    //The prehashed digest has the wrong length
+    InvalidDigest(InvalidDigestLength),
+    //No point R exists for the r and recovery id given, so no public key can be recovered
+    InvalidRecoveryId,
+    //The signature is well formed, but not valid for the message and public key
+    SignatureMismatch,
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationError::ScalarOutOfRange => write!(f, "r and s must be in the range [1, n-1]"),
+            VerificationError::IdentityPoint => write!(f, "the point computed from the signature is the identity point"),
+            VerificationError::InvalidEncoding(error) => write!(f, "invalid signature encoding: {}", error),
+            VerificationError::InvalidDigest(error) => write!(f, "invalid digest: {}", error),
+            VerificationError::InvalidRecoveryId => write!(f, "no public key can be recovered using the recovery id"),
+            VerificationError::SignatureMismatch => write!(f, "the signature does not match the message and public key"),
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+impl From<SignatureEncodingError> for VerificationError {
+    fn from(error: SignatureEncodingError) -> Self {
+        VerificationError::InvalidEncoding(error)
+    }
+}
+
+impl From<InvalidDigestLength> for VerificationError {
+    fn from(error: InvalidDigestLength) -> Self {
+        VerificationError::InvalidDigest(error)
+    }
+}
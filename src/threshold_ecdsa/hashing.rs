@@ -1,13 +1,105 @@
+use std::fmt;
+use std::io::{self, Read};
 use num_bigint::{BigUint, ToBigInt};
-use sha2::{Sha512, Digest};
+use sha2::{Sha256, Sha384, Sha512, Digest};
 use crate::threshold_ecdsa::bedoza::zp_field::{ZpField, ZpFieldElement};
 
+//The hash functions which can be used to hash messages before signing them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    //The original hashing of this project, the full SHA-512 output is reduced mod n
+    //Note signatures using this are not accepted by standard ECDSA verifiers
+    Sha512ReducedModN,
+    //Standard ECDSA (FIPS 186 / SEC1) hashing, where the hash is truncated to the leftmost bits of the order n
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    //The length in bytes of the digests computed by the hash function
+    pub fn digest_length(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 => 32,
+            HashAlgorithm::Sha384 => 48,
+            HashAlgorithm::Sha512 | HashAlgorithm::Sha512ReducedModN => 64,
+        }
+    }
+
+    pub fn digest(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha256 => Sha256::digest(message).to_vec(),
+            HashAlgorithm::Sha384 => Sha384::digest(message).to_vec(),
+            HashAlgorithm::Sha512 | HashAlgorithm::Sha512ReducedModN => Sha512::digest(message).to_vec(),
+        }
+    }
+
+    //Hashes everything read from the reader, without keeping the whole message in memory
+    pub fn digest_reader<R: Read>(&self, reader: R) -> io::Result<Vec<u8>> {
+        match self {
+            HashAlgorithm::Sha256 => digest_reader_with::<Sha256, R>(reader),
+            HashAlgorithm::Sha384 => digest_reader_with::<Sha384, R>(reader),
+            HashAlgorithm::Sha512 | HashAlgorithm::Sha512ReducedModN => digest_reader_with::<Sha512, R>(reader),
+        }
+    }
+}
+
+fn digest_reader_with<D: Digest, R: Read>(mut reader: R) -> io::Result<Vec<u8>> {
+    let mut hasher = D::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+//Returned when a prehashed digest does not have the length of the digests used when signing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidDigestLength {
+    pub expected: usize,
+    pub actual: usize,
+}
+
+impl fmt::Display for InvalidDigestLength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected a digest of {} bytes, but got {} bytes", self.expected, self.actual)
+    }
+}
+
+impl std::error::Error for InvalidDigestLength {}
+
 pub fn hash_string(message: &str, zp_field: ZpField) -> ZpFieldElement {
-    let mut hasher = Sha512::new();
-    hasher.update(message);
-    let result = hasher.finalize();
+    hash_bytes(message.as_bytes(), HashAlgorithm::Sha512ReducedModN, zp_field)
+}
+
+pub fn hash_bytes(message: &[u8], algorithm: HashAlgorithm, zp_field: ZpField) -> ZpFieldElement {
+    let digest = algorithm.digest(message);
+    digest_to_field_element(&digest, algorithm, zp_field).unwrap()
+}
+
+pub fn hash_reader<R: Read>(reader: R, algorithm: HashAlgorithm, zp_field: ZpField) -> io::Result<ZpFieldElement> {
+    let digest = algorithm.digest_reader(reader)?;
+    Ok(digest_to_field_element(&digest, algorithm, zp_field).unwrap())
+}
 
-    let hash_as_biguint = BigUint::from_bytes_be(&result.to_vec());
+//Converts an already computed digest to a field element, the digest must have been computed using the given hash function
+pub fn digest_to_field_element(digest: &[u8], algorithm: HashAlgorithm, zp_field: ZpField) -> Result<ZpFieldElement, InvalidDigestLength> {
+    if digest.len() != algorithm.digest_length() {
+        return Err(InvalidDigestLength { expected: algorithm.digest_length(), actual: digest.len() });
+    }
+    let mut hash_as_biguint = BigUint::from_bytes_be(digest);
+    if algorithm != HashAlgorithm::Sha512ReducedModN {
+        //bits2int from SEC1, i.e. only the leftmost bits of the digest (as many as the bit length of n) are used
+        let digest_bits = 8 * digest.len() as u64;
+        let order_bits = zp_field.p.bits();
+        if digest_bits > order_bits {
+            hash_as_biguint >>= digest_bits - order_bits;
+        }
+    }
     let hash_as_bigint = hash_as_biguint.to_bigint().unwrap();
-    zp_field.create_field_element(hash_as_bigint)
-}
\ No newline at end of file
+    Ok(zp_field.create_field_element(hash_as_bigint))
+}
@@ -0,0 +1,144 @@
+use std::fmt;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::threshold_ecdsa::bedoza::party::ShareName;
+use crate::threshold_ecdsa::error::{SigningError, VerificationError};
+use crate::threshold_ecdsa::hashing::{hash_bytes, HashAlgorithm};
+use crate::threshold_ecdsa::public_key::{PublicKey, PublicKeyError};
+use crate::threshold_ecdsa::signature::Signature;
+use crate::threshold_ecdsa::ThresholdECDSA;
+
+/*
+    JSON Web Signatures (RFC 7515) using ES256, i.e. ECDSA on P-256 with SHA-256 (RFC 7518).
+    A token is BASE64URL(protected header) || '.' || BASE64URL(payload) || '.' || BASE64URL(r||s),
+    where the first two parts are the signing input. JWTs (RFC 7519) are JWS tokens with a JSON claims set as payload.
+*/
+
+pub const ES256: &str = "ES256";
+
+//The protected header of a token
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JwsHeader {
+    pub alg: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typ: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+}
+
+impl JwsHeader {
+    pub fn es256(kid: Option<&str>) -> Self {
+        Self { alg: ES256.to_string(), typ: None, kid: kid.map(str::to_string) }
+    }
+
+    pub fn jwt(kid: Option<&str>) -> Self {
+        Self { typ: Some("JWT".to_string()), ..Self::es256(kid) }
+    }
+}
+
+//The reasons a token can be rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JwsError {
+    //The token does not consist of three base64url encoded parts, or the header is not valid JSON
+    Malformed,
+    //Only ES256 is supported, other algorithms (in particular "none") are refused
+    UnsupportedAlgorithm(String),
+    //The signature is not valid for the signing input and key
+    InvalidSignature(VerificationError),
+}
+
+impl fmt::Display for JwsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JwsError::Malformed => write!(f, "the token is malformed"),
+            JwsError::UnsupportedAlgorithm(alg) => write!(f, "the algorithm {} is not supported", alg),
+            JwsError::InvalidSignature(error) => write!(f, "invalid signature: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for JwsError {}
+
+impl From<VerificationError> for JwsError {
+    fn from(error: VerificationError) -> Self {
+        JwsError::InvalidSignature(error)
+    }
+}
+
+//The signing input BASE64URL(UTF8(header)) || '.' || BASE64URL(payload)
+pub fn signing_input(header: &JwsHeader, payload: &[u8]) -> String {
+    let header_json = serde_json::to_vec(header).unwrap();
+    format!("{}.{}", URL_SAFE_NO_PAD.encode(header_json), URL_SAFE_NO_PAD.encode(payload))
+}
+
+impl ThresholdECDSA {
+    //Signs a payload using a preprocessed tuple, the output is the compact serialization of the token
+    //A header with another algorithm than ES256 is refused before the tuple is used
+    pub fn sign_jws(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, header: &JwsHeader, payload: &[u8]) -> Result<String, SigningError> {
+        if header.alg != ES256 {
+            return Err(JwsError::UnsupportedAlgorithm(header.alg.clone()).into());
+        }
+        let signing_input = signing_input(header, payload);
+        let signature = self.sign_sha256(k, k_inv, sk_j_prime, signing_input.as_bytes(), None)?;
+        Ok(format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature.to_bytes(&self.zp_field))))
+    }
+
+    //Signs a JWT with the given claims, e.g. {"sub": "1234567890", "iat": 1516239022}
+    pub fn sign_jwt(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, claims: &Value, kid: Option<&str>) -> Result<String, SigningError> {
+        let payload = serde_json::to_vec(claims).unwrap();
+        self.sign_jws(k, k_inv, sk_j_prime, &JwsHeader::jwt(kid), &payload)
+    }
+
+    //Verifies a token in compact serialization, the output is the protected header and the payload
+    pub fn verify_jws(&self, pk: PublicKey, token: &str) -> Result<(JwsHeader, Vec<u8>), JwsError> {
+        let parts: Vec<&str> = token.split('.').collect();
+        if parts.len() != 3 {
+            return Err(JwsError::Malformed);
+        }
+        let decode = |part: &str| URL_SAFE_NO_PAD.decode(part).map_err(|_| JwsError::Malformed);
+        let header: JwsHeader = serde_json::from_slice(&decode(parts[0])?).map_err(|_| JwsError::Malformed)?;
+        if header.alg != ES256 {
+            return Err(JwsError::UnsupportedAlgorithm(header.alg));
+        }
+        let payload = decode(parts[1])?;
+        let signature = Signature::from_bytes(&decode(parts[2])?, &self.zp_field).map_err(VerificationError::from)?;
+
+        let signing_input = &token[..parts[0].len() + 1 + parts[1].len()];
+        let h_m = hash_bytes(signing_input.as_bytes(), HashAlgorithm::Sha256, self.zp_field.clone());
+        self.verify_hash(pk, h_m, &signature)?;
+        Ok((header, payload))
+    }
+}
+
+impl PublicKey {
+    //Exports the key as a JSON Web Key (RFC 7517), i.e. {"kty": "EC", "crv": "P-256", "x": ..., "y": ...}
+    pub fn to_jwk(&self) -> Value {
+        let encoded_point = self.to_sec1_bytes(false);
+        json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "x": URL_SAFE_NO_PAD.encode(&encoded_point[1..33]),
+            "y": URL_SAFE_NO_PAD.encode(&encoded_point[33..]),
+        })
+    }
+
+    pub fn from_jwk(jwk: &Value) -> Result<Self, PublicKeyError> {
+        if jwk["kty"] != "EC" || jwk["crv"] != "P-256" {
+            return Err(PublicKeyError::InvalidJwk);
+        }
+        let coordinate = |name: &str| {
+            let encoded = jwk[name].as_str().ok_or(PublicKeyError::InvalidJwk)?;
+            let bytes = URL_SAFE_NO_PAD.decode(encoded).map_err(|_| PublicKeyError::InvalidJwk)?;
+            if bytes.len() != 32 {
+                return Err(PublicKeyError::InvalidJwk);
+            }
+            Ok(bytes)
+        };
+        let mut encoded_point = vec![0x04];
+        encoded_point.extend(coordinate("x")?);
+        encoded_point.extend(coordinate("y")?);
+        PublicKey::from_sec1_bytes(&encoded_point).map_err(|_| PublicKeyError::InvalidJwk)
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+use hmac::{Hmac, Mac};
+use num_bigint::{BigInt, Sign};
+use elliptic_curve::group::Group as _;
+use sha2::Sha512;
+use crate::threshold_ecdsa::bedoza::ec_helpers::bigint_to_scalar;
+use crate::threshold_ecdsa::bedoza::zp_field::{ZpField, ZpFieldElement};
+use crate::threshold_ecdsa::curve::SupportedCurve;
+use crate::threshold_ecdsa::public_key::PublicKey;
+
+/*
+    Public key derivation as in BIP32 non-hardened derivation (adapted to the curve used here).
+    A child key is the parent key tweaked by t, i.e. [sk + t] and pk + t*G, where t is computed from public information only.
+    Thus anyone knowing the parent public key and chain code can compute the child public keys, while only the parties can sign.
+*/
+
+pub type ChainCode = [u8; 32];
+
+//Indices from 2^31 and up are reserved for hardened derivation, which needs the secret key in the clear
+pub const HARDENED_INDEX_START: u32 = 1 << 31;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DerivationError {
+    //Hardened derivation is not possible, since the secret key is never reconstructed
+    HardenedIndex(u32),
+    //The tweak is not smaller than n or the child key is the identity point, BIP32 says to proceed with the next index
+    InvalidChildKey(u32),
+}
+
+impl fmt::Display for DerivationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DerivationError::HardenedIndex(index) => write!(f, "index {} is a hardened index, which is not supported for threshold keys", index),
+            DerivationError::InvalidChildKey(index) => write!(f, "index {} gives an invalid child key, use the next index", index),
+        }
+    }
+}
+
+impl std::error::Error for DerivationError {}
+
+//Computes the tweak t and the child chain code for a child index, i.e. I = HMAC-SHA512(chain code, serP(pk) || ser32(index))
+pub fn derive_tweak<C: SupportedCurve>(pk: &PublicKey<C>, chain_code: &ChainCode, index: u32, zp_field: &ZpField) -> Result<(ZpFieldElement, ChainCode), DerivationError> {
+    if index >= HARDENED_INDEX_START {
+        return Err(DerivationError::HardenedIndex(index));
+    }
+    let mut mac = Hmac::<Sha512>::new_from_slice(chain_code).unwrap();
+    mac.update(&pk.to_sec1_bytes(true));
+    mac.update(&index.to_be_bytes());
+    let i = mac.finalize().into_bytes();
+
+    let tweak = BigInt::from_bytes_be(Sign::Plus, &i[..32]);
+    if tweak >= zp_field.p {
+        return Err(DerivationError::InvalidChildKey(index));
+    }
+    let mut child_chain_code = [0u8; 32];
+    child_chain_code.copy_from_slice(&i[32..]);
+    Ok((tweak, child_chain_code))
+}
+
+//Computes pk + t*G
+pub fn tweak_public_key<C: SupportedCurve>(pk: &PublicKey<C>, tweak: ZpFieldElement) -> Option<PublicKey<C>> {
+    let child_point = pk.to_projective() + C::ProjectivePoint::generator() * bigint_to_scalar::<C>(tweak);
+    PublicKey::from_point(child_point).ok()
+}
+
+//Derives a child public key and chain code, using only public information
+pub fn derive_child_public_key<C: SupportedCurve>(pk: &PublicKey<C>, chain_code: &ChainCode, index: u32, zp_field: &ZpField) -> Result<(PublicKey<C>, ChainCode), DerivationError> {
+    let (tweak, child_chain_code) = derive_tweak(pk, chain_code, index, zp_field)?;
+    let child_pk = tweak_public_key(pk, tweak).ok_or(DerivationError::InvalidChildKey(index))?;
+    Ok((child_pk, child_chain_code))
+}
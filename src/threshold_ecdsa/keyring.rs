@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+use p256::NistP256;
+use crate::threshold_ecdsa::bedoza::party::ShareName;
+use crate::threshold_ecdsa::curve::SupportedCurve;
+use crate::threshold_ecdsa::error::KeyringError;
+use crate::threshold_ecdsa::public_key::PublicKey;
+
+//The identifier j of a user, each user has its own key ([sk_j], pk_j)
+pub type UserId = String;
+
+//The key of a user, i.e. the name of the shares of [sk_j], the public key pk_j and information about the key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyEntry<C: SupportedCurve = NistP256> {
+    pub sk: ShareName,
+    pub pk: PublicKey<C>,
+    pub metadata: BTreeMap<String, String>,
+    pub created_at: SystemTime,
+}
+
+//The keys of all users, the users are listed in order of their ids
+#[derive(Debug, Clone)]
+pub struct Keyring<C: SupportedCurve = NistP256> {
+    keys: BTreeMap<UserId, KeyEntry<C>>,
+}
+
+impl<C: SupportedCurve> Keyring<C> {
+    pub fn new() -> Self {
+        Self { keys: BTreeMap::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn contains(&self, user_id: &str) -> bool {
+        self.keys.contains_key(user_id)
+    }
+
+    pub fn get(&self, user_id: &str) -> Result<&KeyEntry<C>, KeyringError> {
+        self.keys.get(user_id).ok_or_else(|| KeyringError::UnknownUser(user_id.to_string()))
+    }
+
+    //Every user has at most one key, so adding a key for a user who already has one is refused
+    pub fn insert(&mut self, user_id: UserId, entry: KeyEntry<C>) -> Result<(), KeyringError> {
+        if self.contains(&user_id) {
+            return Err(KeyringError::DuplicateUser(user_id));
+        }
+        self.keys.insert(user_id, entry);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, user_id: &str) -> Result<KeyEntry<C>, KeyringError> {
+        self.keys.remove(user_id).ok_or_else(|| KeyringError::UnknownUser(user_id.to_string()))
+    }
+
+    pub fn list(&self) -> impl Iterator<Item = (&UserId, &KeyEntry<C>)> {
+        self.keys.iter()
+    }
+}
+
+impl<C: SupportedCurve> Default for Keyring<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -31,8 +31,7 @@ impl Chooser {
 
     pub fn retrieve(&mut self, m2: Vec<Ciphertext>) -> Plaintext {
         let ciphertext = m2[self.input as usize].clone();
-        let decryption = self.el_gamal.dec(self.sk.clone(), ciphertext);
-        decryption
+        self.el_gamal.dec(self.sk.clone(), ciphertext)
     }
 }
 
@@ -95,7 +95,7 @@ pub struct ElGamal {
 impl ElGamal {
     //The constructure creates a group of prime order q and a generator g
     pub fn new(group: Group) -> Self {
-        Self { group: group }
+        Self { group }
     }
 
     pub fn gen_sk(&self) -> SecretKey {
@@ -104,8 +104,7 @@ impl ElGamal {
 
     //Takes a secret key and outputs a corresponding public key
     pub fn gen_pk(&self, sk: SecretKey) -> PublicKey {
-        let h = self.group.g.modpow(&sk, &self.group.p);
-        h
+        self.group.g.modpow(&sk, &self.group.p)
     }
 
     //Takes some randomness and outputs a random looking public key
@@ -116,20 +115,20 @@ impl ElGamal {
     fn encode_message(&self, m: Plaintext) -> BigInt {
         //Encode the message to a field element
         if (&m + BigInt::from(1u8)).modpow(&self.group.q, &self.group.p) == BigInt::from(1u8) {
-            return (m + BigInt::from(1u8)).modpow(&BigInt::from(1u8), &self.group.p);
+            (m + BigInt::from(1u8)).modpow(&BigInt::from(1u8), &self.group.p)
         }
         else {
-            return (-m - BigInt::from(1u8)).modpow(&BigInt::from(1u8), &self.group.p);
+            (-m - BigInt::from(1u8)).modpow(&BigInt::from(1u8), &self.group.p)
         }
     }
 
     fn decode_message(&self, encoded_m: BigInt) -> Plaintext {
         //Decode the field element to a message
         if encoded_m <= self.group.q {
-            return (encoded_m - BigInt::from(1u8)).modpow(&BigInt::from(1u8), &self.group.p);
+            (encoded_m - BigInt::from(1u8)).modpow(&BigInt::from(1u8), &self.group.p)
         } 
         else {
-            return (-encoded_m - BigInt::from(1u8)).modpow(&BigInt::from(1u8), &self.group.p);
+            (-encoded_m - BigInt::from(1u8)).modpow(&BigInt::from(1u8), &self.group.p)
         }
     }
 
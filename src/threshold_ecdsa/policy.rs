@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use regex::bytes::Regex;
+use crate::threshold_ecdsa::bedoza::party::ShareName;
+
+/*
+    Signing policies, which every party evaluates before it releases its share of s.
+    A policy consists of rules for all keys and rules for specific keys, where a key is identified by the name of its shares [sk_j].
+    Keys tweaked or derived from [sk_j] are identified by [sk_j] as well, such that a child key has the rules and the signing history of its root key.
+    A signature is only produced if all active parties approve it, a refusal by any party aborts the signature.
+*/
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+//The signature a party is asked to contribute to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SigningRequest<'a> {
+    pub key: &'a str,
+    //The message is not known when signing a digest or a stream, in which case rules on the message refuse the request
+    pub message: Option<&'a [u8]>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PolicyRule {
+    //The message must be one of the given messages
+    MessageAllowlist(Vec<Vec<u8>>),
+    //The message must match the regular expression
+    MessageRegex(Regex),
+    //At most max_signatures signatures per key within any period of the given length
+    RateLimit { max_signatures: usize, period: Duration },
+    //Signing is only allowed from start to end, both in seconds after midnight UTC, the window wraps around midnight if end < start
+    TimeWindow { start: u32, end: u32 },
+    //At most the given number of signatures per key in total
+    MaxSignatures(usize),
+}
+
+//The reasons a party can refuse to contribute to a signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyViolation {
+    MessageNotAllowed,
+    MessageUnavailable,
+    RateLimitExceeded,
+    OutsideTimeWindow,
+    MaxSignaturesReached,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyViolation::MessageNotAllowed => write!(f, "the message is not allowed"),
+            PolicyViolation::MessageUnavailable => write!(f, "the message is needed to evaluate the policy"),
+            PolicyViolation::RateLimitExceeded => write!(f, "the rate limit of the key has been exceeded"),
+            PolicyViolation::OutsideTimeWindow => write!(f, "signing is not allowed at this time"),
+            PolicyViolation::MaxSignaturesReached => write!(f, "the maximum number of signatures for the key has been reached"),
+        }
+    }
+}
+
+//A refusal by a party, i.e. the index of the party and the rule it found violated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyRefusal {
+    pub party: usize,
+    pub violation: PolicyViolation,
+}
+
+impl fmt::Display for PolicyRefusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "party {} refused to sign: {}", self.party, self.violation)
+    }
+}
+
+impl std::error::Error for PolicyRefusal {}
+
+//The policy of a single party together with the signatures it has contributed to, which the rate limits are evaluated against
+#[derive(Debug, Clone, Default)]
+pub struct SigningPolicy {
+    rules: Vec<PolicyRule>,
+    key_rules: HashMap<ShareName, Vec<PolicyRule>>,
+    signing_history: HashMap<ShareName, Vec<SystemTime>>,
+}
+
+impl SigningPolicy {
+    //The empty policy approves every request
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //Adds a rule for all keys
+    pub fn add_rule(&mut self, rule: PolicyRule) {
+        self.rules.push(rule);
+    }
+
+    //Adds a rule for the key with the shares [key]
+    pub fn add_key_rule(&mut self, key: ShareName, rule: PolicyRule) {
+        self.key_rules.entry(key).or_default().push(rule);
+    }
+
+    //The number of signatures the party has contributed to using the key
+    pub fn signature_count(&self, key: &str) -> usize {
+        self.signing_history.get(key).map_or(0, |history| history.len())
+    }
+
+    //Checks a request against all rules for its key, at the time now
+    pub fn evaluate(&self, request: &SigningRequest, now: SystemTime) -> Result<(), PolicyViolation> {
+        let key_rules = self.key_rules.get(request.key).into_iter().flatten();
+        for rule in self.rules.iter().chain(key_rules) {
+            self.evaluate_rule(rule, request, now)?;
+        }
+        Ok(())
+    }
+
+    //Records that the party contributed to a signature, should only be called for requests which have been approved
+    pub fn record(&mut self, request: &SigningRequest, now: SystemTime) {
+        self.signing_history.entry(request.key.to_string()).or_default().push(now);
+    }
+
+    fn evaluate_rule(&self, rule: &PolicyRule, request: &SigningRequest, now: SystemTime) -> Result<(), PolicyViolation> {
+        let history = self.signing_history.get(request.key).map_or(&[][..], |history| history.as_slice());
+        match rule {
+            PolicyRule::MessageAllowlist(allowed_messages) => {
+                let message = request.message.ok_or(PolicyViolation::MessageUnavailable)?;
+                if !allowed_messages.iter().any(|allowed| allowed == message) {
+                    return Err(PolicyViolation::MessageNotAllowed);
+                }
+            }
+            PolicyRule::MessageRegex(regex) => {
+                let message = request.message.ok_or(PolicyViolation::MessageUnavailable)?;
+                if !regex.is_match(message) {
+                    return Err(PolicyViolation::MessageNotAllowed);
+                }
+            }
+            PolicyRule::RateLimit { max_signatures, period } => {
+                let recent_signatures = history.iter().filter(|time| now.duration_since(**time).map_or(true, |age| age < *period)).count();
+                if recent_signatures >= *max_signatures {
+                    return Err(PolicyViolation::RateLimitExceeded);
+                }
+            }
+            PolicyRule::TimeWindow { start, end } => {
+                let seconds_since_epoch = now.duration_since(UNIX_EPOCH).map_err(|_| PolicyViolation::OutsideTimeWindow)?.as_secs();
+                let second_of_day = (seconds_since_epoch % SECONDS_PER_DAY) as u32;
+                let inside = if start <= end {
+                    *start <= second_of_day && second_of_day < *end
+                } else {
+                    *start <= second_of_day || second_of_day < *end
+                };
+                if !inside {
+                    return Err(PolicyViolation::OutsideTimeWindow);
+                }
+            }
+            PolicyRule::MaxSignatures(max_signatures) => {
+                if history.len() >= *max_signatures {
+                    return Err(PolicyViolation::MaxSignaturesReached);
+                }
+            }
+        }
+        Ok(())
+    }
+}
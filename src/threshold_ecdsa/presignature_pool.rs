@@ -0,0 +1,125 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use crate::threshold_ecdsa::bedoza::party::ShareName;
+use crate::threshold_ecdsa::curve::SupportedCurve;
+use crate::threshold_ecdsa::error::SigningError;
+use crate::threshold_ecdsa::ThresholdECDSA;
+
+//The output of the user independent preprocessing, i.e. the tuple (<k>, [k^-1])
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Presignature {
+    pub k: ShareName,
+    pub k_inv: ShareName,
+    //Whether the nonce contributions were hedged using a key, see ThresholdECDSA::hedging_key
+    pub hedged: bool,
+}
+
+/*
+    A pool of presignatures which is filled in batches ahead of demand.
+    Every presignature is handed out at most once, and the pool remembers which nonces have been used for signing,
+    such that a nonce can never be used for two signatures (which would leak sk).
+*/
+pub struct PresignaturePool {
+    available: VecDeque<Presignature>,
+    used_nonces: HashSet<ShareName>,
+    batch_size: usize,
+    low_water_mark: usize,
+}
+
+impl PresignaturePool {
+    pub fn new(batch_size: usize, low_water_mark: usize) -> Self {
+        if batch_size == 0 {
+            panic!("The batch size of the presignature pool must be at least 1");
+        }
+        Self {
+            available: VecDeque::new(),
+            used_nonces: HashSet::new(),
+            batch_size,
+            low_water_mark,
+        }
+    }
+
+    //The number of presignatures generated every time the pool is refilled
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    //When fewer presignatures than this are available the pool should be refilled
+    pub fn low_water_mark(&self) -> usize {
+        self.low_water_mark
+    }
+
+    pub fn available(&self) -> usize {
+        self.available.len()
+    }
+
+    pub fn needs_refill(&self) -> bool {
+        self.available.len() <= self.low_water_mark
+    }
+
+    //Adds a freshly generated presignature to the pool
+    pub fn add(&mut self, presignature: Presignature) {
+        self.available.push_back(presignature);
+    }
+
+    //The presignatures which have not been handed out yet
+    pub fn pending(&self) -> impl Iterator<Item = &Presignature> {
+        self.available.iter()
+    }
+
+    //Hands out the oldest presignature, which is removed from the pool such that it can never be handed out again
+    pub fn take(&mut self) -> Option<Presignature> {
+        self.available.pop_front()
+    }
+
+    pub fn is_used(&self, k: &ShareName) -> bool {
+        self.used_nonces.contains(k)
+    }
+
+    pub fn used_nonces(&self) -> impl Iterator<Item = &ShareName> {
+        self.used_nonces.iter()
+    }
+
+    //Marks the nonce of a presignature as used, fails if it has already been used
+    pub fn mark_used(&mut self, k: &ShareName) -> Result<(), SigningError> {
+        if !self.used_nonces.insert(k.clone()) {
+            return Err(SigningError::PresignatureAlreadyUsed(k.clone()));
+        }
+        Ok(())
+    }
+}
+
+//Handle to a thread refilling the presignature pool in the background
+pub struct PresignatureRefillHandle {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl PresignatureRefillHandle {
+    //Stops the refilling and waits for the thread to finish
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        self.thread.join().unwrap();
+    }
+}
+
+//Starts a thread which checks the pool every interval and refills it, when it has reached its low water mark
+pub fn spawn_background_refill<C: SupportedCurve>(tecdsa: Arc<Mutex<ThresholdECDSA<C>>>, interval: Duration) -> PresignatureRefillHandle {
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let thread = thread::spawn(move || {
+        while !thread_stop.load(Ordering::SeqCst) {
+            {
+                let mut tecdsa = tecdsa.lock().unwrap();
+                if tecdsa.presignature_pool().needs_refill() {
+                    tecdsa.refill_presignature_pool();
+                }
+            }
+            thread::sleep(interval);
+        }
+    });
+    PresignatureRefillHandle { stop, thread }
+}
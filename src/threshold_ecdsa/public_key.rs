@@ -0,0 +1,101 @@
+use std::fmt;
+use elliptic_curve::group::Curve as _;
+use elliptic_curve::{AffinePoint, ProjectivePoint};
+use p256::NistP256;
+use crate::threshold_ecdsa::curve::SupportedCurve;
+
+//The reasons an encoded public key can be rejected when parsing it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublicKeyError {
+    //The point is the identity point, which is not a valid public key
+    IdentityPoint,
+    //The bytes are not a SEC1 encoding of a point on the curve
+    InvalidSec1,
+    //The bytes are not a DER encoded SubjectPublicKeyInfo of a key on the curve
+    InvalidDer,
+    //The string is not a PEM encoded SubjectPublicKeyInfo of a key on the curve
+    InvalidPem,
+    //The JSON is not an EC JSON Web Key of a key on the curve
+    InvalidJwk,
+}
+
+impl fmt::Display for PublicKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PublicKeyError::IdentityPoint => write!(f, "the identity point is not a valid public key"),
+            PublicKeyError::InvalidSec1 => write!(f, "invalid SEC1 encoded point"),
+            PublicKeyError::InvalidDer => write!(f, "invalid DER encoded SubjectPublicKeyInfo"),
+            PublicKeyError::InvalidPem => write!(f, "invalid PEM encoded SubjectPublicKeyInfo"),
+            PublicKeyError::InvalidJwk => write!(f, "invalid JSON Web Key"),
+        }
+    }
+}
+
+impl std::error::Error for PublicKeyError {}
+
+//A public key pk_j, i.e. a point on the curve C (P-256 by default) which is never the identity point
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey<C: SupportedCurve = NistP256> {
+    key: elliptic_curve::PublicKey<C>,
+}
+
+impl<C: SupportedCurve> PublicKey<C> {
+    pub fn from_point(point: ProjectivePoint<C>) -> Result<Self, PublicKeyError> {
+        let key = elliptic_curve::PublicKey::from_affine(point.to_affine()).map_err(|_| PublicKeyError::IdentityPoint)?;
+        Ok(Self { key })
+    }
+
+    pub fn to_projective(&self) -> ProjectivePoint<C> {
+        self.key.to_projective()
+    }
+
+    pub fn to_affine(&self) -> AffinePoint<C> {
+        *self.key.as_affine()
+    }
+
+    //Encodes the point as 0x04||x||y (65 bytes for P-256) or, when compressed, as 0x02/0x03||x (33 bytes for P-256)
+    pub fn to_sec1_bytes(&self, compressed: bool) -> Vec<u8> {
+        C::public_key_to_sec1_bytes(&self.key, compressed)
+    }
+
+    //Parses a compressed or uncompressed SEC1 encoded point, checking that it is on the curve
+    pub fn from_sec1_bytes(bytes: &[u8]) -> Result<Self, PublicKeyError> {
+        if bytes == [0x00] {
+            return Err(PublicKeyError::IdentityPoint);
+        }
+        let key = C::public_key_from_sec1_bytes(bytes).ok_or(PublicKeyError::InvalidSec1)?;
+        Ok(Self { key })
+    }
+
+    //Encodes the key as a DER encoded X.509 SubjectPublicKeyInfo, as used by most other systems
+    pub fn to_public_key_der(&self) -> Vec<u8> {
+        C::public_key_to_der(&self.key)
+    }
+
+    pub fn from_public_key_der(der: &[u8]) -> Result<Self, PublicKeyError> {
+        let key = C::public_key_from_der(der).ok_or(PublicKeyError::InvalidDer)?;
+        Ok(Self { key })
+    }
+
+    //Encodes the key as a PEM encoded SubjectPublicKeyInfo, i.e. a "-----BEGIN PUBLIC KEY-----" block
+    pub fn to_public_key_pem(&self) -> String {
+        C::public_key_to_pem(&self.key)
+    }
+
+    pub fn from_public_key_pem(pem: &str) -> Result<Self, PublicKeyError> {
+        let key = C::public_key_from_pem(pem).ok_or(PublicKeyError::InvalidPem)?;
+        Ok(Self { key })
+    }
+}
+
+impl<C: SupportedCurve> From<elliptic_curve::PublicKey<C>> for PublicKey<C> {
+    fn from(key: elliptic_curve::PublicKey<C>) -> Self {
+        Self { key }
+    }
+}
+
+impl<C: SupportedCurve> From<PublicKey<C>> for elliptic_curve::PublicKey<C> {
+    fn from(public_key: PublicKey<C>) -> Self {
+        public_key.key
+    }
+}
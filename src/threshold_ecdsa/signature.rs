@@ -0,0 +1,188 @@
+use std::fmt;
+use num_bigint::{BigInt, Sign};
+use crate::threshold_ecdsa::bedoza::ec_helpers::pad_to_length_big_endian;
+use crate::threshold_ecdsa::bedoza::zp_field::{ZpField, ZpFieldElement};
+
+//The reasons an encoded signature can be rejected when parsing it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureEncodingError {
+    //The raw r||s encoding does not have the length of two scalars
+    InvalidLength { expected: usize, actual: usize },
+    //The bytes are not a (canonical) DER encoding of a sequence of two positive integers
+    InvalidDer,
+    //r or s is not in the range [1, n-1]
+    ScalarOutOfRange,
+}
+
+impl fmt::Display for SignatureEncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureEncodingError::InvalidLength { expected, actual } => write!(f, "expected a signature of {} bytes, but got {} bytes", expected, actual),
+            SignatureEncodingError::InvalidDer => write!(f, "the signature is not a valid DER encoding"),
+            SignatureEncodingError::ScalarOutOfRange => write!(f, "r and s must be in the range [1, n-1]"),
+        }
+    }
+}
+
+impl std::error::Error for SignatureEncodingError {}
+
+//An ECDSA signature (r,s)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub r: ZpFieldElement,
+    pub s: ZpFieldElement,
+}
+
+impl Signature {
+    pub fn new(r: ZpFieldElement, s: ZpFieldElement) -> Self {
+        Self { r, s }
+    }
+
+    //Creates a signature from r and s, checking that both are in the range [1, n-1]
+    pub fn from_scalars(r: ZpFieldElement, s: ZpFieldElement, zp_field: &ZpField) -> Result<Self, SignatureEncodingError> {
+        let in_range = |value: &ZpFieldElement| *value > BigInt::from(0) && *value < zp_field.p;
+        if !in_range(&r) || !in_range(&s) {
+            return Err(SignatureEncodingError::ScalarOutOfRange);
+        }
+        Ok(Self { r, s })
+    }
+
+    //A signature is low-S if s <= n/2, note (r, n-s) is a valid signature whenever (r, s) is
+    pub fn is_low_s(&self, zp_field: &ZpField) -> bool {
+        self.s <= &zp_field.p >> 1
+    }
+
+    //Returns the low-S form of the signature, i.e. s is replaced with n-s if s > n/2
+    pub fn normalize_s(&self, zp_field: &ZpField) -> Self {
+        if self.is_low_s(zp_field) {
+            return self.clone();
+        }
+        Self { r: self.r.clone(), s: &zp_field.p - &self.s }
+    }
+
+    //The length in bytes of each of r and s in the raw encoding, i.e. the byte length of n
+    fn scalar_length(zp_field: &ZpField) -> usize {
+        zp_field.p.bits().div_ceil(8) as usize
+    }
+
+    //Encodes the signature as r||s, both as fixed length big endian integers (64 bytes for P-256)
+    pub fn to_bytes(&self, zp_field: &ZpField) -> Vec<u8> {
+        let scalar_length = Self::scalar_length(zp_field);
+        let mut bytes = pad_to_length_big_endian(&self.r, scalar_length);
+        bytes.extend(pad_to_length_big_endian(&self.s, scalar_length));
+        bytes
+    }
+
+    //Parses an r||s encoded signature
+    pub fn from_bytes(bytes: &[u8], zp_field: &ZpField) -> Result<Self, SignatureEncodingError> {
+        let scalar_length = Self::scalar_length(zp_field);
+        if bytes.len() != 2 * scalar_length {
+            return Err(SignatureEncodingError::InvalidLength { expected: 2 * scalar_length, actual: bytes.len() });
+        }
+        let r = BigInt::from_bytes_be(Sign::Plus, &bytes[..scalar_length]);
+        let s = BigInt::from_bytes_be(Sign::Plus, &bytes[scalar_length..]);
+        Self::from_scalars(r, s, zp_field)
+    }
+
+    //Encodes the signature as the DER encoding of the ASN.1 structure SEQUENCE { r INTEGER, s INTEGER }
+    pub fn to_der(&self) -> Vec<u8> {
+        let mut content = der_integer(&self.r);
+        content.extend(der_integer(&self.s));
+        let mut der = vec![0x30];
+        der.extend(der_length(content.len()));
+        der.extend(content);
+        der
+    }
+
+    //Parses a DER encoded signature, only the canonical (minimal) encoding is accepted
+    pub fn from_der(der: &[u8], zp_field: &ZpField) -> Result<Self, SignatureEncodingError> {
+        let (content, rest) = parse_der_element(der, 0x30)?;
+        if !rest.is_empty() {
+            return Err(SignatureEncodingError::InvalidDer);
+        }
+        let (r_bytes, rest) = parse_der_element(content, 0x02)?;
+        let (s_bytes, rest) = parse_der_element(rest, 0x02)?;
+        if !rest.is_empty() {
+            return Err(SignatureEncodingError::InvalidDer);
+        }
+        let r = parse_der_positive_integer(r_bytes)?;
+        let s = parse_der_positive_integer(s_bytes)?;
+        Self::from_scalars(r, s, zp_field)
+    }
+}
+
+impl TryFrom<&Signature> for p256::ecdsa::Signature {
+    type Error = SignatureEncodingError;
+
+    fn try_from(signature: &Signature) -> Result<Self, Self::Error> {
+        if signature.r.sign() == Sign::Minus || signature.s.sign() == Sign::Minus || signature.r.bits() > 256 || signature.s.bits() > 256 {
+            return Err(SignatureEncodingError::ScalarOutOfRange);
+        }
+        let mut bytes = pad_to_length_big_endian(&signature.r, 32);
+        bytes.extend(pad_to_length_big_endian(&signature.s, 32));
+        p256::ecdsa::Signature::from_slice(&bytes).map_err(|_| SignatureEncodingError::ScalarOutOfRange)
+    }
+}
+
+impl From<p256::ecdsa::Signature> for Signature {
+    fn from(signature: p256::ecdsa::Signature) -> Self {
+        let (r, s) = signature.split_bytes();
+        Self {
+            r: BigInt::from_bytes_be(Sign::Plus, &r),
+            s: BigInt::from_bytes_be(Sign::Plus, &s),
+        }
+    }
+}
+
+pub(crate) fn der_length(length: usize) -> Vec<u8> {
+    if length < 0x80 {
+        return vec![length as u8];
+    }
+    let length_bytes: Vec<u8> = length.to_be_bytes().iter().skip_while(|byte| **byte == 0).cloned().collect();
+    let mut encoded = vec![0x80 | length_bytes.len() as u8];
+    encoded.extend(length_bytes);
+    encoded
+}
+
+pub(crate) fn der_integer(value: &BigInt) -> Vec<u8> {
+    //to_signed_bytes_be gives the minimal two's complement encoding, i.e. a zero byte is prepended if the top bit is set
+    let bytes = value.to_signed_bytes_be();
+    let mut encoded = vec![0x02];
+    encoded.extend(der_length(bytes.len()));
+    encoded.extend(bytes);
+    encoded
+}
+
+//Parses a DER element with the given tag, returns the content and the remaining bytes
+fn parse_der_element(der: &[u8], tag: u8) -> Result<(&[u8], &[u8]), SignatureEncodingError> {
+    if der.len() < 2 || der[0] != tag {
+        return Err(SignatureEncodingError::InvalidDer);
+    }
+    let (length, header_length) = if der[1] < 0x80 {
+        (der[1] as usize, 2)
+    } else {
+        let length_byte_count = (der[1] & 0x7f) as usize;
+        if length_byte_count == 0 || length_byte_count > 4 || der.len() < 2 + length_byte_count || der[2] == 0 {
+            return Err(SignatureEncodingError::InvalidDer);
+        }
+        let length = der[2..2 + length_byte_count].iter().fold(0usize, |acc, byte| (acc << 8) | *byte as usize);
+        if length < 0x80 {
+            return Err(SignatureEncodingError::InvalidDer); //The short form must be used for short lengths
+        }
+        (length, 2 + length_byte_count)
+    };
+    if der.len() < header_length + length {
+        return Err(SignatureEncodingError::InvalidDer);
+    }
+    Ok((&der[header_length..header_length + length], &der[header_length + length..]))
+}
+
+fn parse_der_positive_integer(bytes: &[u8]) -> Result<BigInt, SignatureEncodingError> {
+    if bytes.is_empty() || bytes[0] & 0x80 != 0 {
+        return Err(SignatureEncodingError::InvalidDer); //Empty or negative
+    }
+    if bytes.len() > 1 && bytes[0] == 0 && bytes[1] & 0x80 == 0 {
+        return Err(SignatureEncodingError::InvalidDer); //Unnecessary leading zero
+    }
+    Ok(BigInt::from_bytes_be(Sign::Plus, bytes))
+}
@@ -0,0 +1,374 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use num_bigint::{BigInt, Sign};
+use sha2::{Digest, Sha256, Sha512};
+use crate::threshold_ecdsa::bedoza::party::ShareName;
+use crate::threshold_ecdsa::error::{SigningError, VerificationError};
+use crate::threshold_ecdsa::hashing::{hash_bytes, HashAlgorithm};
+use crate::threshold_ecdsa::public_key::PublicKey;
+use crate::threshold_ecdsa::signature::Signature;
+use crate::threshold_ecdsa::ThresholdECDSA;
+
+/*
+    OpenSSH support for P-256 threshold keys, i.e. the key type ecdsa-sha2-nistp256 (RFC 5656).
+    A key blob is string("ecdsa-sha2-nistp256") || string("nistp256") || string(SEC1(pk)),
+    and a signature blob is string("ecdsa-sha2-nistp256") || string(mpint(r) || mpint(s)), where the signature is computed using SHA-256.
+    Signatures in the sshsig format (as made by ssh-keygen -Y sign, and used for git commit signing) sign
+    "SSHSIG" || string(namespace) || string("") || string(hash algorithm) || string(H(M)), and are armored as
+    -----BEGIN SSH SIGNATURE----- base64("SSHSIG" || uint32(1) || string(key blob) || string(namespace) || string("") || string(hash algorithm) || string(signature blob)) -----END SSH SIGNATURE-----
+    The agent answers the requests of the ssh-agent protocol for the keys in the keyring of a ThresholdECDSA instance.
+*/
+
+pub const KEY_TYPE: &str = "ecdsa-sha2-nistp256";
+const CURVE_NAME: &str = "nistp256";
+const SSHSIG_MAGIC: &[u8] = b"SSHSIG";
+const SSHSIG_VERSION: u32 = 1;
+const SSHSIG_BEGIN: &str = "-----BEGIN SSH SIGNATURE-----";
+const SSHSIG_END: &str = "-----END SSH SIGNATURE-----";
+const SSHSIG_LINE_LENGTH: usize = 70;
+
+//The message types of the ssh-agent protocol that the agent handles
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+//Longer messages are refused, like OpenSSH does
+const MAX_AGENT_MESSAGE_LENGTH: usize = 256 * 1024;
+
+//The reasons an SSH public key or signature can be rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SshError {
+    //The encoding is not valid
+    Malformed,
+    //Only ecdsa-sha2-nistp256 keys are supported
+    UnsupportedKeyType(String),
+    //The signature was made for another namespace, e.g. "file" instead of "git"
+    NamespaceMismatch(String),
+    //The sshsig hash algorithm is neither sha256 nor sha512
+    UnsupportedHashAlgorithm(String),
+    //The signature is not valid for the message and key
+    InvalidSignature(VerificationError),
+    //An sshsig signature needs a namespace, such as "git" or "file"
+    EmptyNamespace,
+}
+
+impl fmt::Display for SshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SshError::Malformed => write!(f, "the encoding is malformed"),
+            SshError::UnsupportedKeyType(key_type) => write!(f, "the key type {} is not supported", key_type),
+            SshError::NamespaceMismatch(namespace) => write!(f, "the signature was made for the namespace {}", namespace),
+            SshError::UnsupportedHashAlgorithm(algorithm) => write!(f, "the hash algorithm {} is not supported", algorithm),
+            SshError::InvalidSignature(error) => write!(f, "invalid signature: {}", error),
+            SshError::EmptyNamespace => write!(f, "the namespace of an sshsig signature cannot be empty"),
+        }
+    }
+}
+
+impl std::error::Error for SshError {}
+
+impl From<VerificationError> for SshError {
+    fn from(error: VerificationError) -> Self {
+        SshError::InvalidSignature(error)
+    }
+}
+
+impl PublicKey {
+    //The key in the SSH wire format, as used in the agent protocol and in sshsig signatures
+    pub fn to_ssh_key_blob(&self) -> Vec<u8> {
+        let mut blob = vec![];
+        put_string(&mut blob, KEY_TYPE.as_bytes());
+        put_string(&mut blob, CURVE_NAME.as_bytes());
+        put_string(&mut blob, &self.to_sec1_bytes(false));
+        blob
+    }
+
+    pub fn from_ssh_key_blob(blob: &[u8]) -> Result<Self, SshError> {
+        let mut reader = SshReader::new(blob);
+        let key_type = reader.read_utf8()?;
+        if key_type != KEY_TYPE {
+            return Err(SshError::UnsupportedKeyType(key_type));
+        }
+        if reader.read_string()? != CURVE_NAME.as_bytes() {
+            return Err(SshError::Malformed);
+        }
+        let pk = PublicKey::from_sec1_bytes(reader.read_string()?).map_err(|_| SshError::Malformed)?;
+        reader.finish()?;
+        Ok(pk)
+    }
+
+    //The key as a line of an authorized_keys or .pub file, i.e. "ecdsa-sha2-nistp256 AAAA... comment"
+    pub fn to_ssh_public_key(&self, comment: &str) -> String {
+        let line = format!("{} {}", KEY_TYPE, STANDARD.encode(self.to_ssh_key_blob()));
+        if comment.is_empty() { line } else { format!("{} {}", line, comment) }
+    }
+
+    //Parses a public key line, the comment is ignored
+    pub fn from_ssh_public_key(line: &str) -> Result<Self, SshError> {
+        let mut fields = line.split_whitespace();
+        let key_type = fields.next().ok_or(SshError::Malformed)?;
+        if key_type != KEY_TYPE {
+            return Err(SshError::UnsupportedKeyType(key_type.to_string()));
+        }
+        let blob = STANDARD.decode(fields.next().ok_or(SshError::Malformed)?).map_err(|_| SshError::Malformed)?;
+        Self::from_ssh_key_blob(&blob)
+    }
+}
+
+//Encodes a signature in the SSH wire format
+pub fn encode_ssh_signature(signature: &Signature) -> Vec<u8> {
+    let mut scalars = vec![];
+    put_mpint(&mut scalars, &signature.r);
+    put_mpint(&mut scalars, &signature.s);
+    let mut blob = vec![];
+    put_string(&mut blob, KEY_TYPE.as_bytes());
+    put_string(&mut blob, &scalars);
+    blob
+}
+
+//The data signed for an sshsig signature on a message
+pub fn sshsig_signed_data(namespace: &str, hash_algorithm: &str, message: &[u8]) -> Result<Vec<u8>, SshError> {
+    if namespace.is_empty() {
+        return Err(SshError::EmptyNamespace);
+    }
+    let message_hash = match hash_algorithm {
+        "sha256" => Sha256::digest(message).to_vec(),
+        "sha512" => Sha512::digest(message).to_vec(),
+        _ => return Err(SshError::UnsupportedHashAlgorithm(hash_algorithm.to_string())),
+    };
+    let mut signed_data = SSHSIG_MAGIC.to_vec();
+    put_string(&mut signed_data, namespace.as_bytes());
+    put_string(&mut signed_data, &[]);
+    put_string(&mut signed_data, hash_algorithm.as_bytes());
+    put_string(&mut signed_data, &message_hash);
+    Ok(signed_data)
+}
+
+impl ThresholdECDSA {
+    //Signs data for SSH using a preprocessed tuple, the output is a signature blob
+    pub fn sign_ssh_data(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, data: &[u8]) -> Result<Vec<u8>, SigningError> {
+        let signature = self.sign_sha256(k, k_inv, sk_j_prime, data, None)?;
+        Ok(encode_ssh_signature(&signature))
+    }
+
+    //Signs a message in the sshsig format using SHA-512 for the message, like ssh-keygen -Y sign -n namespace
+    //pk must be the public key of sk', since it is included in the signature, the signature is verified under pk before it is armored
+    pub fn sign_sshsig(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, pk: PublicKey, namespace: &str, message: &[u8]) -> Result<String, SigningError> {
+        let signed_data = sshsig_signed_data(namespace, "sha512", message)?;
+        let signature_blob = encode_ssh_signature(&self.sign_sha256(k, k_inv, sk_j_prime, &signed_data, Some(pk))?);
+
+        let mut sshsig = SSHSIG_MAGIC.to_vec();
+        sshsig.extend(SSHSIG_VERSION.to_be_bytes());
+        put_string(&mut sshsig, &pk.to_ssh_key_blob());
+        put_string(&mut sshsig, namespace.as_bytes());
+        put_string(&mut sshsig, &[]);
+        put_string(&mut sshsig, b"sha512");
+        put_string(&mut sshsig, &signature_blob);
+
+        let encoded = STANDARD.encode(sshsig);
+        let mut armored = format!("{}\n", SSHSIG_BEGIN);
+        for line in encoded.as_bytes().chunks(SSHSIG_LINE_LENGTH) {
+            armored.push_str(std::str::from_utf8(line).unwrap());
+            armored.push('\n');
+        }
+        armored.push_str(SSHSIG_END);
+        armored.push('\n');
+        Ok(armored)
+    }
+
+    //Verifies an armored sshsig signature on a message, the output is the public key in the signature
+    //The caller must check that the key is one it trusts, e.g. by looking it up in an allowed signers file
+    pub fn verify_sshsig(&self, armored: &str, namespace: &str, message: &[u8]) -> Result<PublicKey, SshError> {
+        let armored = armored.trim();
+        let body = armored.strip_prefix(SSHSIG_BEGIN).and_then(|rest| rest.strip_suffix(SSHSIG_END)).ok_or(SshError::Malformed)?;
+        let encoded: String = body.split_whitespace().collect();
+        let sshsig = STANDARD.decode(encoded).map_err(|_| SshError::Malformed)?;
+
+        let mut reader = SshReader::new(&sshsig);
+        if reader.read_bytes(SSHSIG_MAGIC.len())? != SSHSIG_MAGIC || reader.read_u32()? != SSHSIG_VERSION {
+            return Err(SshError::Malformed);
+        }
+        let pk = PublicKey::from_ssh_key_blob(reader.read_string()?)?;
+        let signature_namespace = reader.read_utf8()?;
+        if signature_namespace != namespace {
+            return Err(SshError::NamespaceMismatch(signature_namespace));
+        }
+        reader.read_string()?;
+        let hash_algorithm = reader.read_utf8()?;
+        let signature_blob = reader.read_string()?;
+        reader.finish()?;
+
+        let signed_data = sshsig_signed_data(namespace, &hash_algorithm, message)?;
+        self.verify_ssh_signature(pk, &signed_data, signature_blob)?;
+        Ok(pk)
+    }
+
+    //Verifies a signature blob on data, e.g. one returned by the agent
+    pub fn verify_ssh_signature(&self, pk: PublicKey, data: &[u8], signature_blob: &[u8]) -> Result<(), SshError> {
+        let mut reader = SshReader::new(signature_blob);
+        let key_type = reader.read_utf8()?;
+        if key_type != KEY_TYPE {
+            return Err(SshError::UnsupportedKeyType(key_type));
+        }
+        let mut scalars = SshReader::new(reader.read_string()?);
+        reader.finish()?;
+        let r = scalars.read_mpint()?;
+        let s = scalars.read_mpint()?;
+        scalars.finish()?;
+
+        let signature = Signature::from_scalars(r, s, &self.zp_field).map_err(VerificationError::from)?;
+        let h_m = hash_bytes(data, HashAlgorithm::Sha256, self.zp_field.clone());
+        Ok(self.verify_hash(pk, h_m, &signature)?)
+    }
+}
+
+//An ssh-agent serving the keys in the keyring of a ThresholdECDSA instance, the comment of a key is the id of its user
+//Every sign request uses a presignature from the pool, and is subject to the signing policies of the parties
+pub struct SshAgent<'a> {
+    tecdsa: &'a mut ThresholdECDSA,
+}
+
+impl<'a> SshAgent<'a> {
+    pub fn new(tecdsa: &'a mut ThresholdECDSA) -> Self {
+        Self { tecdsa }
+    }
+
+    //Accepts connections on the socket one at a time, until accepting a connection fails
+    //Only available on Unix, elsewhere the connections can be handed to handle_connection directly
+    #[cfg(unix)]
+    pub fn serve(&mut self, listener: &UnixListener) -> io::Result<()> {
+        loop {
+            let (stream, _) = listener.accept()?;
+            //A client sending garbage only ends its own connection
+            let _ = self.handle_connection(stream);
+        }
+    }
+
+    //Answers the requests on a connection until the client closes it, the connection is usually a UnixStream
+    pub fn handle_connection<S: Read + Write>(&mut self, mut stream: S) -> io::Result<()> {
+        loop {
+            let mut length = [0u8; 4];
+            match stream.read_exact(&mut length) {
+                Ok(()) => {}
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(error) => return Err(error),
+            }
+            let length = u32::from_be_bytes(length) as usize;
+            if length > MAX_AGENT_MESSAGE_LENGTH {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "the agent message is too long"));
+            }
+            let mut request = vec![0u8; length];
+            stream.read_exact(&mut request)?;
+
+            let response = self.handle_request(&request).unwrap_or_else(|| vec![SSH_AGENT_FAILURE]);
+            stream.write_all(&(response.len() as u32).to_be_bytes())?;
+            stream.write_all(&response)?;
+            stream.flush()?;
+        }
+    }
+
+    //The response to a single request without the length, None if the request is not supported or cannot be answered
+    fn handle_request(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+        let (message_type, contents) = request.split_first()?;
+        match *message_type {
+            SSH_AGENTC_REQUEST_IDENTITIES => {
+                let keyring = self.tecdsa.keyring();
+                let mut response = vec![SSH_AGENT_IDENTITIES_ANSWER];
+                response.extend((keyring.len() as u32).to_be_bytes());
+                for (user_id, entry) in keyring.list() {
+                    put_string(&mut response, &entry.pk.to_ssh_key_blob());
+                    put_string(&mut response, user_id.as_bytes());
+                }
+                Some(response)
+            }
+            SSH_AGENTC_SIGN_REQUEST => {
+                let mut reader = SshReader::new(contents);
+                let key_blob = reader.read_string().ok()?;
+                let data = reader.read_string().ok()?;
+                //The flags only select hash functions for RSA keys
+                reader.read_u32().ok()?;
+                reader.finish().ok()?;
+
+                let user_id = self.tecdsa.keyring().list()
+                    .find(|(_, entry)| entry.pk.to_ssh_key_blob() == key_blob)
+                    .map(|(user_id, _)| user_id.clone())?;
+                let presignature = self.tecdsa.take_presignature();
+                let (k, k_inv, sk_j_prime) = self.tecdsa.user_dependent_preprocessing_for_user(&user_id, presignature.k, presignature.k_inv).ok()?;
+                let signature_blob = self.tecdsa.sign_ssh_data(k, k_inv, sk_j_prime, data).ok()?;
+                let mut response = vec![SSH_AGENT_SIGN_RESPONSE];
+                put_string(&mut response, &signature_blob);
+                Some(response)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn put_string(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    buffer.extend((bytes.len() as u32).to_be_bytes());
+    buffer.extend(bytes);
+}
+
+//An mpint is a minimal two's complement big endian integer, here it is always positive
+fn put_mpint(buffer: &mut Vec<u8>, value: &BigInt) {
+    if *value == BigInt::from(0) {
+        put_string(buffer, &[]);
+    } else {
+        put_string(buffer, &value.to_signed_bytes_be());
+    }
+}
+
+//Reads the fields of the SSH wire format from a buffer
+struct SshReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> SshReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], SshError> {
+        if self.bytes.len() < length {
+            return Err(SshError::Malformed);
+        }
+        let (bytes, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, SshError> {
+        Ok(u32::from_be_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_string(&mut self) -> Result<&'a [u8], SshError> {
+        let length = self.read_u32()? as usize;
+        self.read_bytes(length)
+    }
+
+    fn read_utf8(&mut self) -> Result<String, SshError> {
+        String::from_utf8(self.read_string()?.to_vec()).map_err(|_| SshError::Malformed)
+    }
+
+    //Negative and non-minimal encodings are refused
+    fn read_mpint(&mut self) -> Result<BigInt, SshError> {
+        let bytes = self.read_string()?;
+        match bytes {
+            [] => Ok(BigInt::from(0)),
+            [first, ..] if first & 0x80 != 0 => Err(SshError::Malformed),
+            [0, second, ..] if second & 0x80 == 0 => Err(SshError::Malformed),
+            [0] => Err(SshError::Malformed),
+            _ => Ok(BigInt::from_bytes_be(Sign::Plus, bytes)),
+        }
+    }
+
+    //Refuses trailing data
+    fn finish(&self) -> Result<(), SshError> {
+        if self.bytes.is_empty() { Ok(()) } else { Err(SshError::Malformed) }
+    }
+}
@@ -0,0 +1,290 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use num_bigint::BigInt;
+use crate::threshold_ecdsa::bedoza::party::ShareName;
+use crate::threshold_ecdsa::error::SigningError;
+use crate::threshold_ecdsa::public_key::PublicKey;
+use crate::threshold_ecdsa::signature::{der_integer, der_length};
+use crate::threshold_ecdsa::ThresholdECDSA;
+
+/*
+    DER encoding of PKCS#10 certification requests (RFC 2986) and self-signed X.509 v3 certificates (RFC 5280) for a threshold key.
+    The to be signed part (CertificationRequestInfo or TBSCertificate) is encoded here and signed by the parties with ecdsa-with-SHA256,
+    the output is SEQUENCE { tbs, AlgorithmIdentifier, BIT STRING(DER signature) } in both cases.
+    Only the fields needed for simple certificates are supported: a subject, a validity period, key usage and basic constraints.
+*/
+
+//ecdsa-with-SHA256, 1.2.840.10045.4.3.2
+const OID_ECDSA_WITH_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+//id-at-commonName, 2.5.4.3
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+//id-at-countryName, 2.5.4.6
+const OID_COUNTRY_NAME: &[u8] = &[0x55, 0x04, 0x06];
+//id-at-organizationName, 2.5.4.10
+const OID_ORGANIZATION_NAME: &[u8] = &[0x55, 0x04, 0x0a];
+//id-ce-keyUsage, 2.5.29.15
+const OID_KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x0f];
+//id-ce-basicConstraints, 2.5.29.19
+const OID_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1d, 0x13];
+
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_UTF8_STRING: u8 = 0x0c;
+const TAG_PRINTABLE_STRING: u8 = 0x13;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+
+//The subject of a request or certificate, which is also the issuer of a self-signed certificate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistinguishedName {
+    pub common_name: String,
+    pub organization: Option<String>,
+    //Two letter country code, e.g. "DK"
+    pub country: Option<String>,
+}
+
+impl DistinguishedName {
+    pub fn new(common_name: &str) -> Self {
+        Self { common_name: common_name.to_string(), organization: None, country: None }
+    }
+}
+
+//The bits of the key usage extension, only the ones meaningful for an ECDSA key are included
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyUsage {
+    pub digital_signature: bool,
+    pub non_repudiation: bool,
+    pub key_agreement: bool,
+    pub key_cert_sign: bool,
+    pub crl_sign: bool,
+}
+
+impl KeyUsage {
+    //The bits of the named bit list, i.e. bit 0 is the most significant bit of the first byte
+    fn bits(&self) -> u8 {
+        let named_bits = [
+            (self.digital_signature, 0),
+            (self.non_repudiation, 1),
+            (self.key_agreement, 4),
+            (self.key_cert_sign, 5),
+            (self.crl_sign, 6),
+        ];
+        named_bits.iter().filter(|(set, _)| *set).fold(0, |bits, (_, bit)| bits | (0x80 >> bit))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateParams {
+    pub subject: DistinguishedName,
+    //Must be positive
+    pub serial_number: BigInt,
+    pub not_before: SystemTime,
+    pub not_after: SystemTime,
+    pub key_usage: KeyUsage,
+    //Whether the certificate is a CA certificate, which is recorded in the basic constraints extension
+    pub is_ca: bool,
+}
+
+//The reasons a request or certificate cannot be encoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertificateError {
+    //The serial number of a certificate must be positive
+    NonPositiveSerialNumber,
+    //The certificate expires before it becomes valid
+    InvalidValidity,
+    //At least one key usage must be set, since RFC 5280 does not allow an empty key usage extension
+    EmptyKeyUsage,
+    //The country is not a two letter code
+    InvalidCountry(String),
+    //Times before 1970 are not supported
+    TimeBefore1970,
+}
+
+impl fmt::Display for CertificateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CertificateError::NonPositiveSerialNumber => write!(f, "the serial number must be positive"),
+            CertificateError::InvalidValidity => write!(f, "the certificate expires before it becomes valid"),
+            CertificateError::EmptyKeyUsage => write!(f, "at least one key usage must be set"),
+            CertificateError::InvalidCountry(country) => write!(f, "the country {} is not a two letter code", country),
+            CertificateError::TimeBefore1970 => write!(f, "times before 1970 are not supported"),
+        }
+    }
+}
+
+impl std::error::Error for CertificateError {}
+
+//Encodes the CertificationRequestInfo for the subject and pk, without any attributes
+pub fn certification_request_info(subject: &DistinguishedName, pk: &PublicKey) -> Result<Vec<u8>, CertificateError> {
+    let mut content = der_integer(&BigInt::from(0));
+    content.extend(encode_name(subject)?);
+    content.extend(pk.to_public_key_der());
+    //The attributes are [0] IMPLICIT SET OF Attribute
+    content.extend(der_element(0xa0, &[]));
+    Ok(der_element(TAG_SEQUENCE, &content))
+}
+
+//Encodes the TBSCertificate of a self-signed certificate for pk
+pub fn tbs_certificate(params: &CertificateParams, pk: &PublicKey) -> Result<Vec<u8>, CertificateError> {
+    if params.serial_number <= BigInt::from(0) {
+        return Err(CertificateError::NonPositiveSerialNumber);
+    }
+    if params.not_after < params.not_before {
+        return Err(CertificateError::InvalidValidity);
+    }
+    if params.key_usage == KeyUsage::default() {
+        return Err(CertificateError::EmptyKeyUsage);
+    }
+    let name = encode_name(&params.subject)?;
+
+    //The version is [0] EXPLICIT INTEGER, where 2 means v3
+    let mut content = der_element(0xa0, &der_integer(&BigInt::from(2)));
+    content.extend(der_integer(&params.serial_number));
+    content.extend(signature_algorithm());
+    content.extend(name.clone());
+    let mut validity = encode_time(params.not_before)?;
+    validity.extend(encode_time(params.not_after)?);
+    content.extend(der_element(TAG_SEQUENCE, &validity));
+    content.extend(name);
+    content.extend(pk.to_public_key_der());
+    //The extensions are [3] EXPLICIT SEQUENCE OF Extension
+    content.extend(der_element(0xa3, &der_element(TAG_SEQUENCE, &encode_extensions(params))));
+    Ok(der_element(TAG_SEQUENCE, &content))
+}
+
+//Wraps the to be signed part and a DER encoded signature into a signed request or certificate, the structure is the same for both
+pub fn signed_structure(tbs: &[u8], signature_der: &[u8]) -> Vec<u8> {
+    let mut content = tbs.to_vec();
+    content.extend(signature_algorithm());
+    content.extend(bit_string(0, signature_der));
+    der_element(TAG_SEQUENCE, &content)
+}
+
+//PEM encodes a DER structure with the given label, e.g. "CERTIFICATE" or "CERTIFICATE REQUEST"
+pub fn to_pem(label: &str, der: &[u8]) -> String {
+    let encoded = STANDARD.encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+impl ThresholdECDSA {
+    //Creates a DER encoded PKCS#10 request for pk using a preprocessed tuple, pk must be the public key of sk'
+    pub fn create_csr(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, subject: &DistinguishedName, pk: &PublicKey) -> Result<Vec<u8>, SigningError> {
+        let info = certification_request_info(subject, pk)?;
+        self.sign_tbs(k, k_inv, sk_j_prime, info, pk)
+    }
+
+    //Creates a DER encoded self-signed certificate for pk using a preprocessed tuple, pk must be the public key of sk'
+    pub fn create_self_signed_certificate(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, params: &CertificateParams, pk: &PublicKey) -> Result<Vec<u8>, SigningError> {
+        let tbs = tbs_certificate(params, pk)?;
+        self.sign_tbs(k, k_inv, sk_j_prime, tbs, pk)
+    }
+
+    fn sign_tbs(&mut self, k: ShareName, k_inv: ShareName, sk_j_prime: ShareName, tbs: Vec<u8>, pk: &PublicKey) -> Result<Vec<u8>, SigningError> {
+        let signature = self.sign_sha256(k, k_inv, sk_j_prime, &tbs, Some(*pk))?;
+        Ok(signed_structure(&tbs, &signature.to_der()))
+    }
+}
+
+fn der_element(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut element = vec![tag];
+    element.extend(der_length(content.len()));
+    element.extend(content);
+    element
+}
+
+//The parameters of ecdsa-with-SHA256 must be absent
+fn signature_algorithm() -> Vec<u8> {
+    der_element(TAG_SEQUENCE, &der_element(TAG_OID, OID_ECDSA_WITH_SHA256))
+}
+
+fn bit_string(unused_bits: u8, bytes: &[u8]) -> Vec<u8> {
+    let mut content = vec![unused_bits];
+    content.extend(bytes);
+    der_element(TAG_BIT_STRING, &content)
+}
+
+//A Name is a SEQUENCE of relative distinguished names, each of which is here a SET with a single attribute
+fn encode_name(name: &DistinguishedName) -> Result<Vec<u8>, CertificateError> {
+    let mut attributes = vec![];
+    if let Some(country) = &name.country {
+        if country.len() != 2 || !country.bytes().all(|byte| byte.is_ascii_alphabetic()) {
+            return Err(CertificateError::InvalidCountry(country.clone()));
+        }
+        attributes.push((OID_COUNTRY_NAME, TAG_PRINTABLE_STRING, country));
+    }
+    if let Some(organization) = &name.organization {
+        attributes.push((OID_ORGANIZATION_NAME, TAG_UTF8_STRING, organization));
+    }
+    attributes.push((OID_COMMON_NAME, TAG_UTF8_STRING, &name.common_name));
+
+    let mut content = vec![];
+    for (oid, tag, value) in attributes {
+        let mut attribute = der_element(TAG_OID, oid);
+        attribute.extend(der_element(tag, value.as_bytes()));
+        content.extend(der_element(TAG_SET, &der_element(TAG_SEQUENCE, &attribute)));
+    }
+    Ok(der_element(TAG_SEQUENCE, &content))
+}
+
+//Both extensions are marked critical, as recommended by RFC 5280 for CA certificates
+fn encode_extensions(params: &CertificateParams) -> Vec<u8> {
+    let mut basic_constraints = vec![];
+    if params.is_ca {
+        basic_constraints = der_element(TAG_BOOLEAN, &[0xff]);
+    }
+    let mut extensions = encode_extension(OID_BASIC_CONSTRAINTS, &der_element(TAG_SEQUENCE, &basic_constraints));
+
+    //DER requires the trailing zero bits of a named bit list to be removed, at least one bit is set
+    let bits = params.key_usage.bits();
+    extensions.extend(encode_extension(OID_KEY_USAGE, &bit_string(bits.trailing_zeros() as u8, &[bits])));
+    extensions
+}
+
+fn encode_extension(oid: &[u8], value: &[u8]) -> Vec<u8> {
+    let mut extension = der_element(TAG_OID, oid);
+    extension.extend(der_element(TAG_BOOLEAN, &[0xff]));
+    extension.extend(der_element(TAG_OCTET_STRING, value));
+    der_element(TAG_SEQUENCE, &extension)
+}
+
+//Times in 1950 through 2049 must be encoded as UTCTime, other times as GeneralizedTime
+fn encode_time(time: SystemTime) -> Result<Vec<u8>, CertificateError> {
+    let seconds = time.duration_since(UNIX_EPOCH).map_err(|_| CertificateError::TimeBefore1970)?.as_secs();
+    let (days, second_of_day) = (seconds / 86400, seconds % 86400);
+    let (year, month, day) = civil_from_days(days);
+    let (hour, minute, second) = (second_of_day / 3600, second_of_day % 3600 / 60, second_of_day % 60);
+    if year < 2050 {
+        let utc_time = format!("{:02}{:02}{:02}{:02}{:02}{:02}Z", year % 100, month, day, hour, minute, second);
+        Ok(der_element(TAG_UTC_TIME, utc_time.as_bytes()))
+    } else {
+        let generalized_time = format!("{:04}{:02}{:02}{:02}{:02}{:02}Z", year, month, day, hour, minute, second);
+        Ok(der_element(TAG_GENERALIZED_TIME, generalized_time.as_bytes()))
+    }
+}
+
+//Converts days since 1970-01-01 to (year, month, day) in the proleptic Gregorian calendar
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    //Shift the epoch to 0000-03-01, such that leap days are at the end of a year, and split into 400 year eras
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,181 @@
+use std::collections::HashSet;
+use elliptic_curve::group::Group as _;
+use num_bigint::{BigInt, Sign};
+use p256::NistP256;
+use sha2::{Digest, Sha256};
+use crate::threshold_ecdsa::bedoza::ec_helpers::{bigint_to_scalar, encode_point};
+use crate::threshold_ecdsa::bedoza::party::ShareName;
+use crate::threshold_ecdsa::bedoza::zp_field::{ZpField, ZpFieldElement};
+use crate::threshold_ecdsa::bedoza::{Bedoza, SharingMode};
+use crate::threshold_ecdsa::curve::SupportedCurve;
+use crate::threshold_ecdsa::error::SigningError;
+use crate::threshold_ecdsa::ot::elgamal::Group;
+use crate::threshold_ecdsa::public_key::PublicKey;
+
+//The output of the first round, i.e. the shared nonces [d], [e] and the commitments D_i = d_i*G, E_i = e_i*G of every party
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonceCommitment<C: SupportedCurve = NistP256> {
+    pub d: ShareName,
+    pub e: ShareName,
+    pub commitments: Vec<(usize, C::ProjectivePoint, C::ProjectivePoint)>,
+}
+
+//A Schnorr signature (R, z) such that z*G = R + c*pk, where c = H(R || pk || M)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchnorrSignature<C: SupportedCurve = NistP256> {
+    pub r: C::ProjectivePoint,
+    pub z: ZpFieldElement,
+}
+
+impl<C: SupportedCurve> SchnorrSignature<C> {
+    //Encodes the signature as the compressed SEC1 encoding of R followed by z as a fixed length big endian integer (33 + 32 bytes for P-256)
+    pub fn to_bytes(&self, zp_field: &ZpField) -> Vec<u8> {
+        let mut bytes = encode_point::<C>(&self.r, true);
+        let z_bytes = self.z.to_bytes_be().1;
+        let scalar_length = zp_field.p.bits().div_ceil(8) as usize;
+        bytes.extend(vec![0; scalar_length - z_bytes.len()]);
+        bytes.extend(z_bytes);
+        bytes
+    }
+
+    //Parses an encoded signature, None is returned if R is not a point on the curve or z is not smaller than n
+    pub fn from_bytes(bytes: &[u8], zp_field: &ZpField) -> Option<Self> {
+        let scalar_length = zp_field.p.bits().div_ceil(8) as usize;
+        let point_length = bytes.len().checked_sub(scalar_length)?;
+        let r = PublicKey::<C>::from_sec1_bytes(&bytes[..point_length]).ok()?.to_projective();
+        let z = BigInt::from_bytes_be(Sign::Plus, &bytes[point_length..]);
+        if z >= zp_field.p {
+            return None;
+        }
+        Some(Self { r, z })
+    }
+}
+
+/*
+    Threshold Schnorr signatures in the style of FROST, using the same Bedoza machinery as threshold ECDSA.
+    Signing is linear in the secret key, so no multiplication triples are needed:
+    Round 1: the parties generate random shared nonces [d], [e] and publish the commitments D_i = d_i*G, E_i = e_i*G.
+    Round 2: the binding factor rho = H(pk || commitments || M) fixes the nonce k = d + rho*e and R = D + rho*E,
+             the parties then compute and open [z] = [k] + c*[sk], where c = H(R || pk || M).
+    Since rho depends on the message and all commitments, a malicious party cannot choose its commitment after seeing the others'.
+    As for ThresholdECDSA, the public computation is done here for simplicity.
+*/
+pub struct ThresholdSchnorr<C: SupportedCurve = NistP256> {
+    bedoza: Bedoza<C>,
+    zp_field: ZpField,
+    used_nonces: HashSet<ShareName>,
+}
+
+impl ThresholdSchnorr {
+    pub fn new(ot_group: Group, party_count: usize) -> Self {
+        Self::new_with_sharing_mode(ot_group, party_count, SharingMode::Additive)
+    }
+
+    //Creates an instance where any threshold + 1 of the party_count parties can sign
+    pub fn new_shamir(ot_group: Group, party_count: usize, threshold: usize) -> Self {
+        Self::new_with_sharing_mode(ot_group, party_count, SharingMode::Shamir { threshold })
+    }
+}
+
+impl<C: SupportedCurve> ThresholdSchnorr<C> {
+    pub fn new_with_sharing_mode(ot_group: Group, party_count: usize, sharing_mode: SharingMode) -> Self {
+        let zp_field = ZpField::from_curve_order::<C>();
+        Self {
+            bedoza: Bedoza::new_with_sharing_mode(ot_group, zp_field.clone(), party_count, sharing_mode),
+            zp_field,
+            used_nonces: HashSet::new(),
+        }
+    }
+
+    pub fn zp_field(&self) -> &ZpField {
+        &self.zp_field
+    }
+
+    pub fn bedoza(&self) -> &Bedoza<C> {
+        &self.bedoza
+    }
+
+    //Selects the parties taking part in the following rounds
+    pub fn set_active_parties(&mut self, party_indices: &[usize]) {
+        self.bedoza.set_active_parties(party_indices);
+    }
+
+    //Generate a keypair ([sk], pk), this is the same as for threshold ECDSA
+    pub fn gen_keypair(&mut self) -> (ShareName, PublicKey<C>) {
+        let sk = self.bedoza.rand();
+        self.bedoza.convert_ec(sk.clone());
+        let pk = self.bedoza.open_ec(sk.clone());
+
+        (sk, PublicKey::from_point(pk).expect("The generated public key is the identity point"))
+    }
+
+    //Round 1, can be done before the message is known, every commitment can only be used for one signature
+    pub fn commit_nonces(&mut self) -> NonceCommitment<C> {
+        let d = self.bedoza.rand();
+        let e = self.bedoza.rand();
+        self.bedoza.convert_ec(d.clone());
+        self.bedoza.convert_ec(e.clone());
+        let commitments = self.bedoza.active_parties().iter().map(|index| {
+            let party = self.bedoza.party(*index);
+            (*index, party.open_ec_share(d.clone()), party.open_ec_share(e.clone()))
+        }).collect();
+        NonceCommitment { d, e, commitments }
+    }
+
+    //Round 2, signing a message M using the nonces of a commitment from round 1
+    //Using the same nonces twice would leak sk, so a second signature using the same commitment is refused
+    pub fn sign(&mut self, sk: ShareName, pk: PublicKey<C>, nonce: NonceCommitment<C>, message: &[u8]) -> Result<SchnorrSignature<C>, SigningError> {
+        if !self.used_nonces.insert(nonce.d.clone()) {
+            return Err(SigningError::PresignatureAlreadyUsed(nonce.d));
+        }
+        let rho = self.binding_factor(&pk, &nonce.commitments, message);
+        let k = self.bedoza.local_const_mul(nonce.d.clone(), nonce.e.clone(), BigInt::from(1), rho.clone());
+        let big_d = self.bedoza.open_ec(nonce.d);
+        let big_e = self.bedoza.open_ec(nonce.e);
+        let r = big_d + big_e * bigint_to_scalar::<C>(rho);
+
+        let c = challenge(&r, &pk, message, &self.zp_field);
+        let c_sk = self.bedoza.mul_const(sk, c);
+        let z = self.bedoza.add(k, c_sk);
+        let z_open = self.bedoza.open(z);
+        Ok(SchnorrSignature { r, z: z_open })
+    }
+
+    //Verifying a signature (R, z) on a message M using pk, this is plain single key Schnorr verification
+    pub fn verify(&self, pk: PublicKey<C>, message: &[u8], signature: &SchnorrSignature<C>) -> bool {
+        verify(pk, message, signature, &self.zp_field)
+    }
+
+    fn binding_factor(&self, pk: &PublicKey<C>, commitments: &[(usize, C::ProjectivePoint, C::ProjectivePoint)], message: &[u8]) -> ZpFieldElement {
+        let mut hasher = Sha256::new();
+        hasher.update(b"threshold schnorr binding factor");
+        hasher.update(pk.to_sec1_bytes(true));
+        for (index, big_d_i, big_e_i) in commitments {
+            hasher.update((*index as u32).to_be_bytes());
+            hasher.update(encode_point::<C>(big_d_i, true));
+            hasher.update(encode_point::<C>(big_e_i, true));
+        }
+        hasher.update(message);
+        self.zp_field.create_field_element(BigInt::from_bytes_be(Sign::Plus, &hasher.finalize()))
+    }
+}
+
+//Verifies z*G = R + c*pk, without needing a ThresholdSchnorr instance
+pub fn verify<C: SupportedCurve>(pk: PublicKey<C>, message: &[u8], signature: &SchnorrSignature<C>, zp_field: &ZpField) -> bool {
+    if bool::from(signature.r.is_identity()) || signature.z.sign() == Sign::Minus || signature.z >= zp_field.p {
+        return false;
+    }
+    let c = challenge(&signature.r, &pk, message, zp_field);
+    let left = C::ProjectivePoint::generator() * bigint_to_scalar::<C>(signature.z.clone());
+    let right = signature.r + pk.to_projective() * bigint_to_scalar::<C>(c);
+    left == right
+}
+
+//The challenge c = H(R || pk || M) mod n, where the points are compressed SEC1 encoded
+fn challenge<C: SupportedCurve>(r: &C::ProjectivePoint, pk: &PublicKey<C>, message: &[u8], zp_field: &ZpField) -> ZpFieldElement {
+    let mut hasher = Sha256::new();
+    hasher.update(encode_point::<C>(r, true));
+    hasher.update(pk.to_sec1_bytes(true));
+    hasher.update(message);
+    zp_field.create_field_element(BigInt::from_bytes_be(Sign::Plus, &hasher.finalize()))
+}
@@ -1,15 +1,38 @@
 extern crate cc;
 
 use cc::threshold_ecdsa::bedoza::{self, ec_helpers};
-use cc::threshold_ecdsa::ThresholdECDSA;
+use cc::threshold_ecdsa::{PreprocessedTuple, RecoveryId, Signature, ThresholdECDSA};
+use cc::threshold_ecdsa::signature::SignatureEncodingError;
+use cc::threshold_ecdsa::public_key::{PublicKey, PublicKeyError};
+use cc::threshold_ecdsa::key_derivation::{derive_child_public_key, DerivationError};
+use cc::threshold_ecdsa::error::{KeyringError, RefreshError, SigningError, VerificationError};
+use cc::threshold_ecdsa::presignature_pool::spawn_background_refill;
+use cc::threshold_schnorr::{self, SchnorrSignature, ThresholdSchnorr};
+use cc::threshold_ecdsa::ecies::{self, EciesError};
+use cc::threshold_ecdsa::bedoza::backup::{self, BackupError};
+use cc::threshold_ecdsa::bedoza::hedged::HedgedGenerator;
+use cc::threshold_ecdsa::jws::{JwsError, JwsHeader};
+use cc::threshold_ecdsa::x509::{self, CertificateError, CertificateParams, DistinguishedName, KeyUsage};
+use cc::threshold_ecdsa::ssh::{SshAgent, SshError};
+use cc::threshold_ecdsa::dkg::{self, DkgError};
+use cc::threshold_ecdsa::audit::{AuditError, AuditLog, AuditOutcome, AuditQuery, AuditRecord};
+use cc::threshold_ecdsa::batch_verification::BatchEntry;
+use cc::threshold_ecdsa::policy::{PolicyRefusal, PolicyRule, PolicyViolation, SigningPolicy, SigningRequest};
+use std::sync::{Arc, Mutex};
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use cc::threshold_ecdsa::{ot::elgamal::Group, ot::elgamal::ElGamal};
 use cc::threshold_ecdsa::bedoza::zp_field::ZpField;
 use num_bigint::BigInt;
 use alphabet::*;
 
 
-use p256::ProjectivePoint;
-use cc::threshold_ecdsa::hashing::hash_string;
+use p256::{NistP256, ProjectivePoint};
+use cc::threshold_ecdsa::hashing::{hash_string, HashAlgorithm, InvalidDigestLength};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
+use p256::ecdsa::VerifyingKey;
 
 fn load_groups() -> (Group, ZpField) {
     let common_group = Group::struct_from_file("group512.txt");
@@ -90,12 +113,14 @@ fn test_zp_field() {
         let random_element = zp_field.generate_random_element();
         assert!(random_element < zp_field.p);
     }
+    //The field derived from the curve order is the one stored in the file
+    assert_eq!(ZpField::from_curve_order::<NistP256>().p, zp_field.p);
 }
 
 #[test]
 fn test_bedoza_random_generator() {
     let (common_group, zp_field) = load_groups();
-    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone());
+    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone(), 2);
 
     for _ in 0..100 {
         let name = bedoza.rand();
@@ -106,13 +131,13 @@ fn test_bedoza_random_generator() {
 }
 
 #[test]
-fn test_bedoza_alice_identity() {
+fn test_bedoza_dealer_identity() {
     let (common_group, zp_field) = load_groups();
-    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone());
+    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone(), 2);
 
     for i in 0..10 {
         let elem = zp_field.create_field_element(BigInt::from(i));      
-        let name = bedoza.create_secret_sharing_by_alice(zp_field.create_field_element(elem.clone()));
+        let name = bedoza.create_secret_sharing_by_party(0, zp_field.create_field_element(elem.clone()));
         let opened_share_value = bedoza.open(name.clone());
         assert_eq!(elem, opened_share_value);
     }
@@ -121,11 +146,11 @@ fn test_bedoza_alice_identity() {
 #[test]
 fn test_bedoza_add_const() {
     let (common_group, zp_field) = load_groups();
-    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone());
+    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone(), 2);
 
     for i in 0..10 {
         let elem = zp_field.create_field_element(BigInt::from(i));      
-        let name = bedoza.create_secret_sharing_by_alice(elem.clone());
+        let name = bedoza.create_secret_sharing_by_party(0, elem.clone());
         let name2 = bedoza.add_const(name.clone(), zp_field.create_field_element(BigInt::from(2*i)));
         let opened_share_value = bedoza.open(name2.clone());
         assert_eq!(zp_field.create_field_element(BigInt::from(3*i)), opened_share_value);
@@ -136,11 +161,11 @@ fn test_bedoza_add_const() {
 #[test]
 fn test_bedoza_local_multiplication() {
     let (common_group, zp_field) = load_groups();
-    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone());
+    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone(), 2);
 
     for i in 0..10 {
         let elem = zp_field.create_field_element(BigInt::from(i));      
-        let name = bedoza.create_secret_sharing_by_alice(elem.clone());
+        let name = bedoza.create_secret_sharing_by_party(0, elem.clone());
         let name2 = bedoza.mul_const(name.clone(), zp_field.create_field_element(BigInt::from(2*i)));
         let opened_share_value = bedoza.open(name2.clone());
         assert_eq!(zp_field.create_field_element(BigInt::from(i*(2*i))), opened_share_value);
@@ -150,14 +175,14 @@ fn test_bedoza_local_multiplication() {
 #[test]
 fn test_bedoza_adding_shares() {
     let (common_group, zp_field) = load_groups();
-    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone());
+    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone(), 2);
 
     for i in 0..10 {
         let a = zp_field.create_field_element(BigInt::from(i));
         let b = zp_field.create_field_element(BigInt::from(3*i));
 
-        let name_a = bedoza.create_secret_sharing_by_alice(a.clone());
-        let name_b = bedoza.create_secret_sharing_by_bob(b.clone());
+        let name_a = bedoza.create_secret_sharing_by_party(0, a.clone());
+        let name_b = bedoza.create_secret_sharing_by_party(1, b.clone());
 
         let name_c = bedoza.add(name_a.clone(), name_b.clone());
         let opened_share_value = bedoza.open(name_c.clone());
@@ -168,7 +193,7 @@ fn test_bedoza_adding_shares() {
 #[test]
 fn test_rand_mul() {
     let (common_group, zp_field) = load_groups();
-    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone());
+    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone(), 2);
 
     let (u,v,w) = bedoza.rand_mul();
     let u_value = bedoza.open(u.clone());
@@ -180,7 +205,7 @@ fn test_rand_mul() {
 #[test]
 fn test_local_const_mul() {
     let (common_group, zp_field) = load_groups();
-    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone());
+    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone(), 2);
 
     for i in 0..10 {
         let a = zp_field.create_field_element(BigInt::from(i));
@@ -190,8 +215,8 @@ fn test_local_const_mul() {
         //I.e we have c = x * a + y * b
         //Thus c = 9*i + 7*3*i = 30*i
 
-        let name_a = bedoza.create_secret_sharing_by_alice(a.clone());
-        let name_b = bedoza.create_secret_sharing_by_bob(b.clone());
+        let name_a = bedoza.create_secret_sharing_by_party(0, a.clone());
+        let name_b = bedoza.create_secret_sharing_by_party(1, b.clone());
 
         let name_c = bedoza.local_const_mul(name_a.clone(), name_b.clone(), x.clone(), y.clone());
         let opened_share_value = bedoza.open(name_c.clone());
@@ -202,18 +227,20 @@ fn test_local_const_mul() {
 #[test]
 fn test_multiplication() {
     let (common_group, zp_field) = load_groups();
-    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone());
+    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone(), 2);
 
     for i in 0..10 {
         let a_value = zp_field.create_field_element(BigInt::from(i));
         let b_value = zp_field.create_field_element(BigInt::from(3*i));
 
-        let name_a = bedoza.create_secret_sharing_by_alice(a_value.clone());
-        let name_b = bedoza.create_secret_sharing_by_bob(b_value.clone());
+        let name_a = bedoza.create_secret_sharing_by_party(0, a_value.clone());
+        let name_b = bedoza.create_secret_sharing_by_party(1, b_value.clone());
 
         let name_c = bedoza.mul(name_a.clone(), name_b.clone());
         let opened_share_value = bedoza.open(name_c.clone());
         assert_eq!(zp_field.create_field_element(BigInt::from(3*i*i)), opened_share_value);
+        //Only a, b and c are left, the intermediate values of the multiplication are erased
+        assert_eq!(bedoza.party(0).share_count(), (3 * (i + 1)) as usize);
     }
 }
 
@@ -221,17 +248,17 @@ fn test_multiplication() {
 #[test]
 fn test_ec_share_homomorphism() {
     let (common_group, zp_field) = load_groups();
-    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone());
+    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone(), 2);
 
     for _ in 0..20 {
         let zp_elem = zp_field.generate_random_element();
         
         //Creating point diretly from scalar
-        let scalar = ec_helpers::bigint_to_scalar(zp_elem.clone());
+        let scalar = ec_helpers::bigint_to_scalar::<NistP256>(zp_elem.clone());
         let directly_created_point = ProjectivePoint::GENERATOR * scalar;
 
         //Creating point by using shares
-        let share_name = bedoza.create_secret_sharing_by_alice(zp_elem.clone());
+        let share_name = bedoza.create_secret_sharing_by_party(0, zp_elem.clone());
         bedoza.convert_ec(share_name.clone());
         let opened_share_point = bedoza.open_ec(share_name.clone());
 
@@ -242,15 +269,1236 @@ fn test_ec_share_homomorphism() {
 
 #[test]
 fn test_signatures() {
-    let (common_group, zp_field) = load_groups();
-    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), zp_field.clone());
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 2, AuditLog::in_memory());
     let (sk, pk) = tecdsa.gen_keypair();
     let (k, k_inv) = tecdsa.user_independent_preprocessing();
     let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk, k, k_inv);
 
     let m = "Hello world!";
-    let s = tecdsa.sign(k, k_inv, sk_j_prime, m);
+    let s = tecdsa.sign(k, k_inv, sk_j_prime, m).unwrap();
 
     assert!(tecdsa.verify_signature(pk, m, s))
 }
 
+
+#[test]
+fn test_n_party_multiplication() {
+    let (common_group, zp_field) = load_groups();
+    let mut bedoza = bedoza::Bedoza::new(common_group.clone(), zp_field.clone(), 4);
+
+    for i in 0..10 {
+        let a_value = zp_field.create_field_element(BigInt::from(i));
+        let b_value = zp_field.create_field_element(BigInt::from(5*i));
+
+        let name_a = bedoza.create_secret_sharing_by_party(i % 4, a_value.clone());
+        let name_b = bedoza.create_secret_sharing_by_party(3, b_value.clone());
+
+        let name_c = bedoza.mul(name_a.clone(), name_b.clone());
+        let name_d = bedoza.add_const(name_c.clone(), zp_field.create_field_element(BigInt::from(1)));
+        let opened_share_value = bedoza.open(name_d.clone());
+        assert_eq!(zp_field.create_field_element(BigInt::from(5*i*i + 1)), opened_share_value);
+    }
+}
+
+#[test]
+fn test_n_party_signatures() {
+    let (common_group, _) = load_groups();
+    for party_count in 3..=5 {
+        let mut tecdsa = ThresholdECDSA::new(common_group.clone(), party_count, AuditLog::in_memory());
+        let (sk, pk) = tecdsa.gen_keypair();
+        let (k, k_inv) = tecdsa.user_independent_preprocessing();
+        let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk, k, k_inv);
+
+        let m = "Hello world!";
+        let s = tecdsa.sign(k, k_inv, sk_j_prime, m).unwrap();
+
+        assert!(tecdsa.verify_signature(pk, m, s))
+    }
+}
+
+#[test]
+fn test_shamir_open_by_any_subset() {
+    let (common_group, zp_field) = load_groups();
+    let mut bedoza = bedoza::Bedoza::new_shamir(common_group.clone(), zp_field.clone(), 5, 2);
+
+    let a_value = zp_field.create_field_element(BigInt::from(7));
+    let b_value = zp_field.create_field_element(BigInt::from(6));
+    let name_a = bedoza.create_secret_sharing_by_party(1, a_value.clone());
+    let name_b = bedoza.create_secret_sharing_by_party(4, b_value.clone());
+    let name_r = bedoza.rand();
+    let r_value = bedoza.open(name_r.clone());
+
+    //All 5 parties are active, so the product is computed with degree reduction
+    let name_c = bedoza.mul(name_a.clone(), name_b.clone());
+    let name_d = bedoza.add_const(name_c.clone(), zp_field.create_field_element(BigInt::from(3)));
+
+    for subset in [vec![0, 1, 2], vec![2, 3, 4], vec![0, 2, 4], vec![1, 3, 4, 0]] {
+        bedoza.set_active_parties(&subset);
+        assert_eq!(bedoza.open(name_r.clone()), r_value);
+        assert_eq!(bedoza.open(name_d.clone()), zp_field.create_field_element(BigInt::from(45)));
+
+        //Fewer than 2t + 1 parties are active, so the product is computed using a multiplication triple
+        let name_e = bedoza.mul(name_a.clone(), name_d.clone());
+        assert_eq!(bedoza.open(name_e), zp_field.create_field_element(BigInt::from(315)));
+    }
+}
+
+#[test]
+fn test_shamir_signatures() {
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new_shamir(common_group.clone(), 5, 2, AuditLog::in_memory());
+    let (sk, pk) = tecdsa.gen_keypair();
+
+    for subset in [vec![0, 1, 2], vec![1, 3, 4], vec![0, 2, 4]] {
+        tecdsa.set_active_parties(&subset);
+        let (k, k_inv) = tecdsa.user_independent_preprocessing();
+        let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), k, k_inv);
+
+        let m = "Hello world!";
+        let s = tecdsa.sign(k, k_inv, sk_j_prime, m).unwrap();
+
+        assert!(tecdsa.verify_signature(pk, m, s))
+    }
+}
+
+#[test]
+fn test_presignature_pool_single_use() {
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory());
+    tecdsa.configure_presignature_pool(4, 1);
+    let (sk, pk) = tecdsa.gen_keypair();
+
+    tecdsa.refill_presignature_pool();
+    assert_eq!(tecdsa.presignature_pool().available(), 4);
+
+    //Every presignature is handed out once, and the pool is refilled when it reaches the low water mark
+    let mut handed_out = Vec::new();
+    for _ in 0..6 {
+        handed_out.push(tecdsa.take_presignature());
+    }
+    assert_eq!(tecdsa.presignature_pool().available(), 2);
+    for (i, presignature) in handed_out.iter().enumerate() {
+        assert!(handed_out[i + 1..].iter().all(|other| other.k != presignature.k));
+    }
+
+    let presignature = handed_out.pop().unwrap();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+    let s = tecdsa.sign(k.clone(), k_inv.clone(), sk_j_prime.clone(), "first message").unwrap();
+    assert!(tecdsa.verify_signature(pk, "first message", s));
+
+    //Signing a second message with the same nonce must be refused
+    let reuse = tecdsa.sign(k.clone(), k_inv, sk_j_prime, "second message");
+    assert_eq!(reuse, Err(SigningError::PresignatureAlreadyUsed(k)));
+}
+
+#[test]
+fn test_presignature_pool_background_refill() {
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 2, AuditLog::in_memory());
+    tecdsa.configure_presignature_pool(3, 2);
+    let tecdsa = Arc::new(Mutex::new(tecdsa));
+
+    let handle = spawn_background_refill(tecdsa.clone(), Duration::from_millis(5));
+    for _ in 0..1000 {
+        if tecdsa.lock().unwrap().presignature_pool().available() >= 3 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    handle.stop();
+
+    let mut tecdsa = tecdsa.lock().unwrap();
+    assert!(!tecdsa.presignature_pool().needs_refill());
+    let available = tecdsa.presignature_pool().available();
+    tecdsa.take_presignature();
+    assert_eq!(tecdsa.presignature_pool().available(), available - 1);
+}
+
+#[test]
+fn test_sign_bytes_digest_and_reader() {
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 2, AuditLog::in_memory());
+    let (sk, pk) = tecdsa.gen_keypair();
+    let message: Vec<u8> = (0..=255u8).cycle().take(20000).collect(); //Binary message, which is not valid UTF-8
+    let digest = Sha256::digest(&message);
+
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+    let bytes_signature = tecdsa.sign_bytes(k, k_inv, sk_j_prime, &message).unwrap();
+    assert!(tecdsa.verify_bytes(pk, &message, bytes_signature.clone()));
+    assert_eq!(tecdsa.verify_digest(pk, &digest, bytes_signature.clone()), Ok(true));
+
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+    let digest_signature = tecdsa.sign_digest(k, k_inv, sk_j_prime, &digest).unwrap();
+    assert!(tecdsa.verify_bytes(pk, &message, digest_signature));
+
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+    let reader_signature = tecdsa.sign_reader(k, k_inv, sk_j_prime, message.as_slice()).unwrap();
+    assert!(tecdsa.verify_bytes(pk, &message, reader_signature.clone()));
+    assert!(!tecdsa.verify_bytes(pk, &message[1..], reader_signature));
+
+    //Digests of the wrong length are rejected, both when signing and verifying
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk, presignature.k, presignature.k_inv);
+    let short_digest = [0u8; 20];
+    let expected_error = InvalidDigestLength { expected: 32, actual: 20 };
+    assert_eq!(tecdsa.sign_digest(k, k_inv, sk_j_prime, &short_digest), Err(SigningError::InvalidDigest(expected_error.clone())));
+    assert_eq!(tecdsa.verify_digest(pk, &short_digest, bytes_signature), Err(expected_error));
+}
+
+#[test]
+fn test_standard_ecdsa_hashing_interoperability() {
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory());
+    let (sk, pk) = tecdsa.gen_keypair();
+    let verifying_key = VerifyingKey::from_affine(pk.to_affine()).unwrap();
+    let m = b"Standard ECDSA message";
+
+    //The default is ECDSA-P256-SHA256, which is what the p256 crate verifies
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+    let signature = tecdsa.sign_bytes(k, k_inv, sk_j_prime, m).unwrap();
+    assert!(verifying_key.verify(m, &p256::ecdsa::Signature::try_from(&signature).unwrap()).is_ok());
+
+    //Longer hashes are truncated to the bit length of n
+    for (hash_algorithm, digest) in [(HashAlgorithm::Sha384, Sha384::digest(m).to_vec()), (HashAlgorithm::Sha512, Sha512::digest(m).to_vec())] {
+        tecdsa.set_hash_algorithm(hash_algorithm);
+        let presignature = tecdsa.take_presignature();
+        let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+        let signature = tecdsa.sign_bytes(k, k_inv, sk_j_prime, m).unwrap();
+        assert!(tecdsa.verify_bytes(pk, m, signature.clone()));
+        assert!(verifying_key.verify_prehash(&digest, &p256::ecdsa::Signature::try_from(&signature).unwrap()).is_ok());
+    }
+
+    //The original hashing still works with the threshold verifier, but is not standard
+    tecdsa.set_hash_algorithm(HashAlgorithm::Sha512ReducedModN);
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk, presignature.k, presignature.k_inv);
+    let signature = tecdsa.sign_bytes(k, k_inv, sk_j_prime, m).unwrap();
+    assert!(tecdsa.verify_bytes(pk, m, signature.clone()));
+    assert!(verifying_key.verify_prehash(&Sha512::digest(m), &p256::ecdsa::Signature::try_from(&signature).unwrap()).is_err());
+}
+
+#[test]
+fn test_signature_encodings() {
+    let (common_group, zp_field) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 2, AuditLog::in_memory());
+    tecdsa.set_low_s_normalization(true);
+    let (sk, pk) = tecdsa.gen_keypair();
+    let verifying_key = VerifyingKey::from_affine(pk.to_affine()).unwrap();
+    let m = "Encode me";
+
+    for _ in 0..5 {
+        let presignature = tecdsa.take_presignature();
+        let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+        let signature = tecdsa.sign(k, k_inv, sk_j_prime, m).unwrap();
+        assert!(signature.is_low_s(&zp_field));
+        assert!(tecdsa.verify_signature(pk, m, signature.clone()));
+
+        //DER and r||s encodings agree with the p256 crate
+        let p256_signature = p256::ecdsa::Signature::try_from(&signature).unwrap();
+        assert_eq!(signature.to_der(), p256_signature.to_der().as_bytes());
+        assert_eq!(signature.to_bytes(&zp_field), p256_signature.to_bytes().to_vec());
+        assert_eq!(Signature::from(p256_signature), signature);
+        assert!(verifying_key.verify(m.as_bytes(), &p256_signature).is_ok());
+
+        assert_eq!(Signature::from_der(&signature.to_der(), &zp_field), Ok(signature.clone()));
+        assert_eq!(Signature::from_bytes(&signature.to_bytes(&zp_field), &zp_field), Ok(signature.clone()));
+
+        //The high-S form is also valid, and normalizes back
+        let high_s = Signature::new(signature.r.clone(), &zp_field.p - &signature.s);
+        assert!(!high_s.is_low_s(&zp_field));
+        assert!(tecdsa.verify_signature(pk, m, high_s.clone()));
+        assert_eq!(high_s.normalize_s(&zp_field), signature);
+    }
+
+    //Out of range scalars and malformed encodings are rejected
+    let n = zp_field.p.clone();
+    let out_of_range = Signature::new(BigInt::from(1), n.clone());
+    assert_eq!(Signature::from_bytes(&out_of_range.to_bytes(&zp_field), &zp_field), Err(SignatureEncodingError::ScalarOutOfRange));
+    assert_eq!(Signature::from_der(&out_of_range.to_der(), &zp_field), Err(SignatureEncodingError::ScalarOutOfRange));
+    assert_eq!(Signature::from_der(&Signature::new(BigInt::from(0), BigInt::from(5)).to_der(), &zp_field), Err(SignatureEncodingError::ScalarOutOfRange));
+    assert_eq!(Signature::from_bytes(&[1u8; 63], &zp_field), Err(SignatureEncodingError::InvalidLength { expected: 64, actual: 63 }));
+    assert_eq!(Signature::from_der(&[0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x02, 0x00, 0x01], &zp_field), Err(SignatureEncodingError::InvalidDer));
+    assert_eq!(Signature::from_der(&[0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01, 0x00], &zp_field), Err(SignatureEncodingError::InvalidDer));
+}
+
+#[test]
+fn test_verification_rejects_malformed_inputs() {
+    let (common_group, zp_field) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 2, AuditLog::in_memory());
+    let (sk, pk) = tecdsa.gen_keypair();
+    let m = b"Untrusted input";
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk, presignature.k, presignature.k_inv);
+    let signature = tecdsa.sign_bytes(k, k_inv, sk_j_prime, m).unwrap();
+    assert_eq!(tecdsa.try_verify(pk, m, &signature), Ok(()));
+
+    let n = zp_field.p.clone();
+    let out_of_range = [
+        Signature::new(signature.r.clone(), BigInt::from(0)),
+        Signature::new(BigInt::from(0), signature.s.clone()),
+        Signature::new(signature.r.clone(), n.clone()),
+        Signature::new(&n + 1, signature.s.clone()),
+        Signature::new(signature.r.clone(), BigInt::from(-1)),
+        Signature::new(signature.r.clone(), &n << 300),
+    ];
+    for bad_signature in out_of_range {
+        assert_eq!(tecdsa.try_verify(pk, m, &bad_signature), Err(VerificationError::ScalarOutOfRange));
+        assert!(!tecdsa.verify_bytes(pk, m, bad_signature));
+    }
+
+    //The identity point can not be used as a public key
+    assert_eq!(PublicKey::<NistP256>::from_point(ProjectivePoint::IDENTITY), Err(PublicKeyError::IdentityPoint));
+    assert_eq!(PublicKey::<NistP256>::from_sec1_bytes(&[0x00]), Err(PublicKeyError::IdentityPoint));
+    assert_eq!(tecdsa.try_verify(pk, b"Other message", &signature), Err(VerificationError::SignatureMismatch));
+
+    //Canonical encodings verify, while non-canonical ones (here with long form length) are rejected
+    let der = signature.to_der();
+    assert_eq!(tecdsa.try_verify_der(pk, m, &der), Ok(()));
+    assert_eq!(tecdsa.try_verify_raw(pk, m, &signature.to_bytes(&zp_field)), Ok(()));
+    let mut non_canonical_der = vec![0x30, 0x81];
+    non_canonical_der.extend(&der[1..]);
+    assert_eq!(tecdsa.try_verify_der(pk, m, &non_canonical_der), Err(VerificationError::InvalidEncoding(SignatureEncodingError::InvalidDer)));
+    let mut trailing_der = der.clone();
+    trailing_der.push(0);
+    assert_eq!(tecdsa.try_verify_der(pk, m, &trailing_der), Err(VerificationError::InvalidEncoding(SignatureEncodingError::InvalidDer)));
+    assert!(tecdsa.try_verify_raw(pk, m, &[0u8; 64]).is_err());
+}
+
+#[test]
+fn test_public_key_encodings() {
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory());
+    let (sk, pk) = tecdsa.gen_keypair();
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk, presignature.k, presignature.k_inv);
+    let m = "Published key";
+    let signature = tecdsa.sign(k, k_inv, sk_j_prime, m).unwrap();
+
+    let uncompressed = pk.to_sec1_bytes(false);
+    let compressed = pk.to_sec1_bytes(true);
+    assert_eq!(uncompressed.len(), 65);
+    assert_eq!(uncompressed[0], 0x04);
+    assert_eq!(compressed.len(), 33);
+    assert!(compressed[0] == 0x02 || compressed[0] == 0x03);
+    let der = pk.to_public_key_der();
+    let pem = pk.to_public_key_pem();
+    assert!(pem.starts_with("-----BEGIN PUBLIC KEY-----"));
+
+    //All parsed keys are equal to the original and can be used to verify signatures
+    let parsed_keys = [
+        PublicKey::from_sec1_bytes(&uncompressed).unwrap(),
+        PublicKey::from_sec1_bytes(&compressed).unwrap(),
+        PublicKey::from_public_key_der(&der).unwrap(),
+        PublicKey::from_public_key_pem(&pem).unwrap(),
+    ];
+    for parsed_key in parsed_keys {
+        assert_eq!(parsed_key, pk);
+        assert!(tecdsa.verify_signature(parsed_key, m, signature.clone()));
+    }
+
+    //The encodings match the p256 crate
+    assert_eq!(der, p256::pkcs8::EncodePublicKey::to_public_key_der(&p256::PublicKey::from(pk)).unwrap().as_bytes());
+
+    //Malformed encodings and points not on the curve are rejected
+    let mut not_on_curve = uncompressed.clone();
+    not_on_curve[64] ^= 1;
+    assert_eq!(PublicKey::<NistP256>::from_sec1_bytes(&not_on_curve), Err(PublicKeyError::InvalidSec1));
+    assert_eq!(PublicKey::<NistP256>::from_sec1_bytes(&compressed[..32]), Err(PublicKeyError::InvalidSec1));
+    assert_eq!(PublicKey::<NistP256>::from_public_key_der(&der[..der.len() - 1]), Err(PublicKeyError::InvalidDer));
+    assert_eq!(PublicKey::<NistP256>::from_public_key_pem(&pem.replace("PUBLIC KEY", "PRIVATE KEY")), Err(PublicKeyError::InvalidPem));
+}
+
+#[test]
+fn test_proactive_key_refresh() {
+    let (common_group, zp_field) = load_groups();
+    for mut tecdsa in [ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory()), ThresholdECDSA::new_shamir(common_group.clone(), 4, 1, AuditLog::in_memory())] {
+        let (sk, pk) = tecdsa.gen_keypair();
+        let party_count = tecdsa.bedoza().party_count();
+        let sk_value = tecdsa.bedoza().open(sk.clone());
+        let old_shares: Vec<BigInt> = (0..party_count).map(|i| tecdsa.bedoza().party(i).open_share(sk.clone())).collect();
+
+        //A tuple preprocessed before the refresh, a tweaked child key, and presignatures waiting in the pool
+        let presignature = tecdsa.take_presignature();
+        let pending_tuple = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+        let (child_sk, _) = tecdsa.tweak_key(sk.clone(), pk, BigInt::from(7)).unwrap();
+        let dependent_shares = [pending_tuple.1.clone(), pending_tuple.2.clone(), child_sk.clone()];
+        let old_dependent_shares: Vec<BigInt> = dependent_shares.iter().map(|share| tecdsa.bedoza().party(0).open_share(share.clone())).collect();
+        let dependent_values: Vec<BigInt> = dependent_shares.iter().map(|share| tecdsa.bedoza().open(share.clone())).collect();
+
+        tecdsa.refresh_key(sk.clone()).unwrap();
+
+        //The shares of the tuple and of the child key are refreshed too, since sk_j can be computed from either
+        for ((share, old_share), value) in dependent_shares.iter().zip(old_dependent_shares).zip(dependent_values) {
+            assert_ne!(tecdsa.bedoza().party(0).open_share(share.clone()), old_share);
+            assert_eq!(tecdsa.bedoza().open(share.clone()), value);
+        }
+
+        //The key is unchanged, but every share is new
+        let new_shares: Vec<BigInt> = (0..party_count).map(|i| tecdsa.bedoza().party(i).open_share(sk.clone())).collect();
+        for (old_share, new_share) in old_shares.iter().zip(new_shares.iter()) {
+            assert_ne!(old_share, new_share);
+        }
+        assert_eq!(tecdsa.bedoza().open(sk.clone()), sk_value);
+        assert_eq!(PublicKey::from_point(tecdsa.bedoza().open_ec(sk.clone())).unwrap(), pk);
+
+        //Old shares combined with new shares do not give the key
+        let mut mixed_shares = new_shares.clone();
+        mixed_shares[0] = old_shares[0].clone();
+        let mixed_value = match tecdsa.bedoza().sharing_mode() {
+            bedoza::SharingMode::Additive => mixed_shares.iter().fold(BigInt::from(0), |acc, share| zp_field.add(acc, share.clone())),
+            bedoza::SharingMode::Shamir { .. } => {
+                let lagrange = bedoza::shamir::lagrange_coefficients(&zp_field, &[0, 1]);
+                zp_field.add(zp_field.mul(lagrange[0].clone(), mixed_shares[0].clone()), zp_field.mul(lagrange[1].clone(), mixed_shares[1].clone()))
+            }
+        };
+        assert_ne!(mixed_value, sk_value);
+
+        //Both the tuple preprocessed before the refresh and the refreshed presignatures can still be used
+        let (k, k_inv, sk_j_prime) = pending_tuple;
+        let signature = tecdsa.sign(k, k_inv, sk_j_prime, "Before refresh").unwrap();
+        assert!(tecdsa.verify_signature(pk, "Before refresh", signature));
+
+        let presignature = tecdsa.take_presignature();
+        let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+        let signature = tecdsa.sign(k, k_inv, sk_j_prime, "After refresh").unwrap();
+        assert!(tecdsa.verify_signature(pk, "After refresh", signature));
+    }
+
+    //Only the parties holding a share take part in refreshing it, and every one of them must be active
+    let mut tecdsa = ThresholdECDSA::new_shamir(common_group, 3, 1, AuditLog::in_memory());
+    let (sk, pk) = tecdsa.gen_keypair();
+    tecdsa.set_active_parties(&[0, 1]);
+    tecdsa.refill_presignature_pool();
+    tecdsa.set_active_parties(&[0, 1, 2]);
+    tecdsa.refresh_key(sk.clone()).unwrap();
+    tecdsa.set_active_parties(&[0, 1]);
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+    let signature = tecdsa.sign(k, k_inv, sk_j_prime, "Subset refresh").unwrap();
+    assert!(tecdsa.verify_signature(pk, "Subset refresh", signature));
+    assert_eq!(tecdsa.refresh_key(sk.clone()), Err(RefreshError::InactiveParty { share: sk, party: 2 }));
+    assert!(matches!(tecdsa.refresh_key("unknown".to_string()), Err(RefreshError::UnknownShare(_))));
+}
+
+#[test]
+fn test_child_key_derivation() {
+    let (common_group, zp_field) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory());
+    let (sk, pk) = tecdsa.gen_keypair();
+    let chain_code = [7u8; 32];
+
+    //Tweaking adds the tweak to the secret key
+    let tweak = BigInt::from(12345);
+    let (tweaked_sk, tweaked_pk) = tecdsa.tweak_key(sk.clone(), pk, tweak.clone()).unwrap();
+    assert_eq!(tecdsa.bedoza().open(tweaked_sk), zp_field.add(tecdsa.bedoza().open(sk.clone()), tweak));
+    assert_eq!(PublicKey::from_point(pk.to_projective() + ProjectivePoint::GENERATOR * ec_helpers::bigint_to_scalar::<NistP256>(BigInt::from(12345))).unwrap(), tweaked_pk);
+
+    let (child_sk, child_pk, child_chain_code) = tecdsa.derive_child_key(sk.clone(), pk, &chain_code, 0).unwrap();
+    let (grandchild_sk, grandchild_pk, _) = tecdsa.derive_child_key(child_sk.clone(), child_pk, &child_chain_code, 42).unwrap();
+    assert_ne!(child_pk, pk);
+
+    //Anyone with the public key and chain code can derive the same public keys
+    let (public_child_pk, public_child_chain_code) = derive_child_public_key(&pk, &chain_code, 0, &zp_field).unwrap();
+    assert_eq!((public_child_pk, public_child_chain_code), (child_pk, child_chain_code));
+    assert_eq!(derive_child_public_key(&child_pk, &child_chain_code, 42, &zp_field).unwrap().0, grandchild_pk);
+    assert_ne!(derive_child_public_key(&pk, &chain_code, 1, &zp_field).unwrap().0, child_pk);
+
+    //Child keys sign through the normal presignature path
+    for (key_sk, key_pk) in [(child_sk, child_pk), (grandchild_sk, grandchild_pk)] {
+        let presignature = tecdsa.take_presignature();
+        let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(key_sk, presignature.k, presignature.k_inv);
+        let signature = tecdsa.sign(k, k_inv, sk_j_prime, "Derived key").unwrap();
+        assert!(tecdsa.verify_signature(key_pk, "Derived key", signature.clone()));
+        assert!(!tecdsa.verify_signature(pk, "Derived key", signature));
+    }
+
+    //Hardened derivation needs the secret key in the clear and is refused
+    assert_eq!(tecdsa.derive_child_key(sk, pk, &chain_code, 1 << 31), Err(DerivationError::HardenedIndex(1 << 31)));
+}
+
+#[test]
+fn test_public_key_recovery() {
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory());
+    let (sk, pk) = tecdsa.gen_keypair();
+    let m = b"Recover my signer";
+
+    for low_s_normalization in [false, true, false, true] {
+        tecdsa.set_low_s_normalization(low_s_normalization);
+        let presignature = tecdsa.take_presignature();
+        let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+        let (signature, recovery_id) = tecdsa.sign_recoverable(k, k_inv, sk_j_prime, m).unwrap();
+        assert!(tecdsa.verify_bytes(pk, m, signature.clone()));
+
+        assert_eq!(tecdsa.recover_public_key(m, &signature, recovery_id), Ok(pk));
+
+        //The p256 crate recovers the same key
+        let p256_signature = p256::ecdsa::Signature::try_from(&signature).unwrap();
+        let recovered = VerifyingKey::recover_from_msg(m, &p256_signature, recovery_id).unwrap();
+        assert_eq!(recovered, VerifyingKey::from_affine(pk.to_affine()).unwrap());
+
+        //The opposite parity gives -R, and another message gives another hash, both of which lead to a different key
+        let wrong_recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+        assert_ne!(tecdsa.recover_public_key(m, &signature, wrong_recovery_id), Ok(pk));
+        assert_ne!(tecdsa.recover_public_key(b"Other message", &signature, recovery_id), Ok(pk));
+    }
+
+    let zero_signature = Signature::new(BigInt::from(0), BigInt::from(1));
+    assert_eq!(tecdsa.recover_public_key(m, &zero_signature, RecoveryId::from_byte(0).unwrap()), Err(VerificationError::ScalarOutOfRange));
+}
+
+#[test]
+fn test_p384_signatures() {
+    let (common_group, _) = load_groups();
+    for sharing_mode in [bedoza::SharingMode::Additive, bedoza::SharingMode::Shamir { threshold: 1 }] {
+        let mut tecdsa = ThresholdECDSA::<p384::NistP384>::new_with_sharing_mode(common_group.clone(), 3, sharing_mode, AuditLog::in_memory());
+        tecdsa.set_hash_algorithm(HashAlgorithm::Sha384);
+        let n = BigInt::parse_bytes(b"ffffffffffffffffffffffffffffffffffffffffffffffffc7634d81f4372ddf581a0db248b0a77aecec196accc52973", 16).unwrap();
+        assert_eq!(tecdsa.zp_field().p, n);
+
+        let (sk, pk) = tecdsa.gen_keypair();
+        assert_eq!(pk.to_sec1_bytes(true).len(), 49);
+        let presignature = tecdsa.take_presignature();
+        let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+        let (signature, recovery_id) = tecdsa.sign_recoverable(k, k_inv, sk_j_prime, b"Hello P-384").unwrap();
+        assert!(tecdsa.verify_bytes(pk, b"Hello P-384", signature.clone()));
+        assert!(!tecdsa.verify_bytes(pk, b"Hello P-256", signature.clone()));
+        assert_eq!(tecdsa.recover_public_key(b"Hello P-384", &signature, recovery_id), Ok(pk));
+
+        //The p384 crate accepts the signature
+        let raw = signature.to_bytes(tecdsa.zp_field());
+        assert_eq!(raw.len(), 96);
+        let p384_signature = p384::ecdsa::Signature::from_slice(&raw).unwrap();
+        let verifying_key = p384::ecdsa::VerifyingKey::from_affine(pk.to_affine()).unwrap();
+        assert!(verifying_key.verify(b"Hello P-384", &p384_signature).is_ok());
+    }
+}
+
+#[test]
+fn test_threshold_schnorr_signatures() {
+    let (common_group, _) = load_groups();
+    for mut schnorr in [ThresholdSchnorr::new(common_group.clone(), 3), ThresholdSchnorr::new_shamir(common_group.clone(), 4, 1)] {
+        let (sk, pk) = schnorr.gen_keypair();
+        if schnorr.bedoza().party_count() == 4 {
+            schnorr.set_active_parties(&[1, 3]);
+        }
+
+        let nonce = schnorr.commit_nonces();
+        assert_eq!(nonce.commitments.len(), schnorr.bedoza().active_parties().len());
+        let signature = schnorr.sign(sk.clone(), pk, nonce.clone(), b"Hello Schnorr").unwrap();
+        assert!(schnorr.verify(pk, b"Hello Schnorr", &signature));
+        assert!(threshold_schnorr::verify(pk, b"Hello Schnorr", &signature, schnorr.zp_field()));
+        assert!(!schnorr.verify(pk, b"Hello ECDSA", &signature));
+
+        //The nonces of a commitment can only be used once
+        assert_eq!(schnorr.sign(sk.clone(), pk, nonce.clone(), b"Other message"), Err(SigningError::PresignatureAlreadyUsed(nonce.d)));
+
+        let bytes = signature.to_bytes(schnorr.zp_field());
+        assert_eq!(bytes.len(), 65);
+        assert_eq!(SchnorrSignature::from_bytes(&bytes, schnorr.zp_field()), Some(signature.clone()));
+        let tampered = SchnorrSignature::<NistP256> { r: signature.r, z: schnorr.zp_field().add(signature.z.clone(), BigInt::from(1)) };
+        assert!(!schnorr.verify(pk, b"Hello Schnorr", &tampered));
+        assert_eq!(SchnorrSignature::<NistP256>::from_bytes(&bytes[1..], schnorr.zp_field()), None);
+    }
+}
+
+#[test]
+fn test_threshold_ecdh_and_ecies() {
+    let (common_group, _) = load_groups();
+    for mut tecdsa in [ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory()), ThresholdECDSA::new_shamir(common_group.clone(), 4, 1, AuditLog::in_memory())] {
+        let (sk, pk) = tecdsa.gen_keypair();
+        if tecdsa.bedoza().party_count() == 4 {
+            tecdsa.set_active_parties(&[0, 2]);
+        }
+
+        //Diffie-Hellman with a peer using p256, both sides get the same point
+        let peer_secret = p256::SecretKey::random(&mut rand::thread_rng());
+        let peer_pk = PublicKey::from(peer_secret.public_key());
+        let shared_point = tecdsa.threshold_ecdh(sk.clone(), &peer_pk);
+        assert_eq!(shared_point, pk.to_projective() * *peer_secret.to_nonzero_scalar());
+
+        let ciphertext = ecies::encrypt(&pk, b"Secret message");
+        assert_eq!(tecdsa.decrypt(sk.clone(), &ciphertext), Ok(b"Secret message".to_vec()));
+
+        //The key is unchanged, so it can still be used for signing
+        let presignature = tecdsa.take_presignature();
+        let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+        let signature = tecdsa.sign(k, k_inv, sk_j_prime, "Same key").unwrap();
+        assert!(tecdsa.verify_signature(pk, "Same key", signature));
+
+        let mut tampered = ciphertext.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(tecdsa.decrypt(sk.clone(), &tampered), Err(EciesError::DecryptionFailed));
+        let (_, other_pk) = tecdsa.gen_keypair();
+        assert_eq!(tecdsa.decrypt(sk.clone(), &ecies::encrypt(&other_pk, b"Not for sk")), Err(EciesError::DecryptionFailed));
+        assert_eq!(tecdsa.decrypt(sk.clone(), &ciphertext[..70]), Err(EciesError::Truncated));
+        let mut invalid_key = ciphertext.clone();
+        invalid_key[64] ^= 1;
+        assert_eq!(tecdsa.decrypt(sk, &invalid_key), Err(EciesError::InvalidEphemeralKey));
+    }
+}
+
+#[test]
+fn test_sign_batch() {
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory());
+    let (sk, pk) = tecdsa.gen_keypair();
+    let (other_sk, _) = tecdsa.gen_keypair();
+    let messages: Vec<Vec<u8>> = (0..10).map(|i| format!("Batch message {}", i).into_bytes()).collect();
+
+    let mut tuples = Vec::new();
+    for i in 0..messages.len() {
+        let presignature = tecdsa.take_presignature();
+        //Entry 7 is preprocessed with another key, so it is refused before its nonce is opened
+        let key = if i == 7 { other_sk.clone() } else { sk.clone() };
+        tuples.push(tecdsa.user_dependent_preprocessing(key, presignature.k, presignature.k_inv));
+    }
+    //Entry 3 uses a nonce which has already been used for a signature
+    let used = tuples[3].clone();
+    tecdsa.sign(used.0.clone(), used.1, used.2, "Already signed").unwrap();
+
+    let entries: Vec<(PreprocessedTuple, &[u8])> = tuples.into_iter().zip(messages.iter().map(|m| m.as_slice())).collect();
+    let rounds_before = tecdsa.bedoza().opening_rounds();
+    let results = tecdsa.sign_batch(pk, &entries);
+    assert_eq!(tecdsa.bedoza().opening_rounds() - rounds_before, 2);
+
+    assert_eq!(results.len(), messages.len());
+    for (i, result) in results.into_iter().enumerate() {
+        match i {
+            3 => assert_eq!(result, Err(SigningError::PresignatureAlreadyUsed(used.0.clone()))),
+            7 => assert_eq!(result, Err(SigningError::KeyMismatch(entries[7].0.2.clone()))),
+            _ => assert!(tecdsa.verify_bytes(pk, &messages[i], result.unwrap())),
+        }
+    }
+}
+
+#[test]
+fn test_keyring() {
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory());
+    let metadata = BTreeMap::from([("purpose".to_string(), "payments".to_string())]);
+    let alice_pk = tecdsa.gen_user_keypair("alice", metadata.clone()).unwrap();
+    let bob_pk = tecdsa.gen_user_keypair("bob", BTreeMap::new()).unwrap();
+    assert_ne!(alice_pk, bob_pk);
+    assert_eq!(tecdsa.gen_user_keypair("alice", BTreeMap::new()), Err(KeyringError::DuplicateUser("alice".to_string())));
+
+    assert_eq!(tecdsa.list_users(), vec!["alice".to_string(), "bob".to_string()]);
+    assert_eq!(tecdsa.public_key("alice"), Ok(alice_pk));
+    let alice_entry = tecdsa.keyring().get("alice").unwrap();
+    assert_eq!(alice_entry.metadata, metadata);
+    assert!(alice_entry.created_at <= SystemTime::now());
+
+    //Signing by user id uses the key of that user
+    let signature = tecdsa.sign_for_user("alice", b"From alice").unwrap();
+    assert!(tecdsa.verify_bytes(alice_pk, b"From alice", signature.clone()));
+    assert!(!tecdsa.verify_bytes(bob_pk, b"From alice", signature));
+
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing_for_user("bob", presignature.k, presignature.k_inv).unwrap();
+    let signature = tecdsa.sign(k, k_inv, sk_j_prime, "From bob").unwrap();
+    assert!(tecdsa.verify_signature(bob_pk, "From bob", signature));
+
+    //Deleting a key erases the shares held by the parties, also of the keys derived from it and of the tuples preprocessed using them
+    let alice_sk = tecdsa.keyring().get("alice").unwrap().sk.clone();
+    let (child_sk, _) = tecdsa.tweak_key(alice_sk, alice_pk, BigInt::from(1)).unwrap();
+    let presignature = tecdsa.take_presignature();
+    let (_, _, sk_j_prime) = tecdsa.user_dependent_preprocessing(child_sk.clone(), presignature.k, presignature.k_inv);
+    let deleted = tecdsa.delete_user_key("alice").unwrap();
+    assert_eq!(deleted.pk, alice_pk);
+    assert_eq!(tecdsa.list_users(), vec!["bob".to_string()]);
+    for share in [deleted.sk.clone(), child_sk, sk_j_prime] {
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| tecdsa.bedoza().open(share.clone()))).is_err());
+    }
+    assert_eq!(tecdsa.sign_for_user("alice", b"Deleted"), Err(SigningError::Keyring(KeyringError::UnknownUser("alice".to_string()))));
+    assert_eq!(tecdsa.delete_user_key("alice").map(|entry| entry.pk), Err(KeyringError::UnknownUser("alice".to_string())));
+}
+
+#[test]
+fn test_signing_policies() {
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory());
+    let (sk, pk) = tecdsa.gen_keypair();
+    let (other_sk, other_pk) = tecdsa.gen_keypair();
+
+    let mut party_1_policy = SigningPolicy::new();
+    party_1_policy.add_key_rule(sk.clone(), PolicyRule::MessageRegex(regex::bytes::Regex::new(r"^transfer:\d+$").unwrap()));
+    tecdsa.set_signing_policy(1, party_1_policy);
+    let mut party_2_policy = SigningPolicy::new();
+    party_2_policy.add_rule(PolicyRule::MaxSignatures(2));
+    tecdsa.set_signing_policy(2, party_2_policy);
+
+    let sign = |tecdsa: &mut ThresholdECDSA, key: &String, message: &[u8]| {
+        let presignature = tecdsa.take_presignature();
+        let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(key.clone(), presignature.k, presignature.k_inv);
+        tecdsa.sign_bytes(k, k_inv, sk_j_prime, message)
+    };
+    let refused = |party, violation| Err(SigningError::PolicyRefused(PolicyRefusal { party, violation }));
+
+    let signature = sign(&mut tecdsa, &sk, b"transfer:10").unwrap();
+    assert!(tecdsa.verify_bytes(pk, b"transfer:10", signature));
+    assert_eq!(sign(&mut tecdsa, &sk, b"withdraw:10"), refused(1, PolicyViolation::MessageNotAllowed));
+    //The rules of party 1 are only for sk
+    let signature = sign(&mut tecdsa, &other_sk, b"withdraw:10").unwrap();
+    assert!(tecdsa.verify_bytes(other_pk, b"withdraw:10", signature));
+    //A zero tweak gives another name for the same key, which is still subject to the rules of sk
+    let (tweaked_sk, _) = tecdsa.tweak_key(sk.clone(), pk, BigInt::from(0)).unwrap();
+    assert_eq!(sign(&mut tecdsa, &tweaked_sk, b"withdraw:10"), refused(1, PolicyViolation::MessageNotAllowed));
+    //A tuple which was not preprocessed has no known key
+    let presignature = tecdsa.take_presignature();
+    assert_eq!(tecdsa.sign_bytes(presignature.k, presignature.k_inv, sk.clone(), b"transfer:10"), Err(SigningError::UnknownKey(sk.clone())));
+
+    //Without the message party 1 can not check its rule
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+    assert_eq!(tecdsa.sign_digest(k, k_inv, sk_j_prime, &Sha256::digest(b"transfer:10")), refused(1, PolicyViolation::MessageUnavailable));
+
+    //Refused signatures do not count, so sk has one signature left
+    assert!(sign(&mut tecdsa, &sk, b"transfer:20").is_ok());
+    assert_eq!(sign(&mut tecdsa, &sk, b"transfer:30"), refused(2, PolicyViolation::MaxSignaturesReached));
+
+    //Rate limits and time windows are evaluated at a given time
+    let mut policy = SigningPolicy::new();
+    policy.add_rule(PolicyRule::RateLimit { max_signatures: 2, period: Duration::from_secs(60) });
+    policy.add_rule(PolicyRule::TimeWindow { start: 22 * 3600, end: 6 * 3600 });
+    let request = SigningRequest { key: "A", message: Some(b"Hello") };
+    let midnight = UNIX_EPOCH + Duration::from_secs(20000 * 24 * 3600);
+    assert_eq!(policy.evaluate(&request, midnight + Duration::from_secs(12 * 3600)), Err(PolicyViolation::OutsideTimeWindow));
+    for seconds in [0, 10] {
+        assert_eq!(policy.evaluate(&request, midnight + Duration::from_secs(seconds)), Ok(()));
+        policy.record(&request, midnight + Duration::from_secs(seconds));
+    }
+    assert_eq!(policy.evaluate(&request, midnight + Duration::from_secs(30)), Err(PolicyViolation::RateLimitExceeded));
+    assert_eq!(policy.evaluate(&request, midnight + Duration::from_secs(65)), Ok(()));
+    assert_eq!(policy.evaluate(&SigningRequest { key: "B", message: None }, midnight + Duration::from_secs(30)), Ok(()));
+    assert_eq!(policy.signature_count("A"), 2);
+}
+
+#[test]
+fn test_share_backup_and_restore() {
+    let (common_group, zp_field) = load_groups();
+    let mut bedoza = bedoza::Bedoza::new_shamir(common_group.clone(), zp_field.clone(), 3, 1);
+    let sk = bedoza.rand();
+    bedoza.convert_ec(sk.clone());
+    let pk = PublicKey::from_point(bedoza.open_ec(sk.clone())).unwrap();
+    let sk_value = bedoza.open(sk.clone());
+
+    let backup = bedoza.party(1).export_share_backup(1, &sk, &pk, "correct horse battery staple");
+    let other_backup = bedoza.party(2).export_share_backup(2, &sk, &pk, "correct horse battery staple");
+
+    //Party 1 loses its shares and restores them from the backup
+    bedoza.party_mut(1).delete_share(&sk);
+    assert_eq!(bedoza.restore_share(1, &backup, "wrong passphrase"), Err(BackupError::DecryptionFailed));
+    assert_eq!(bedoza.restore_share(1, &other_backup, "correct horse battery staple"), Err(BackupError::WrongParty { expected: 1, actual: 2 }));
+    let mut tampered = backup.clone();
+    tampered[30] ^= 1;
+    assert_eq!(bedoza.restore_share(1, &tampered, "correct horse battery staple"), Err(BackupError::DecryptionFailed));
+    assert_eq!(bedoza.restore_share(1, &backup[..20], "correct horse battery staple"), Err(BackupError::Malformed));
+    assert_eq!(bedoza.restore_share(1, &backup, "correct horse battery staple"), Ok(sk.clone()));
+
+    bedoza.set_active_parties(&[0, 1]);
+    assert_eq!(bedoza.open(sk.clone()), sk_value);
+    assert_eq!(bedoza.open_ec(sk.clone()), pk.to_projective());
+
+    //A backup of another key does not combine with the shares of the other parties to its public key
+    let (_, other_pk) = ThresholdECDSA::new(common_group.clone(), 2, AuditLog::in_memory()).gen_keypair();
+    bedoza.set_active_parties(&[0, 1, 2]);
+    let wrong_key_backup = bedoza.party(1).export_share_backup(1, &sk, &other_pk, "passphrase");
+    assert_eq!(bedoza.restore_share(1, &wrong_key_backup, "passphrase"), Err(BackupError::PublicKeyMismatch));
+    //A public key which is not a valid SEC1 encoding makes the backup malformed
+    let mut invalid_key_backup = backup::decrypt_backup(&wrong_key_backup, "passphrase").unwrap();
+    invalid_key_backup.public_key = vec![0x04, 1, 2, 3];
+    let invalid_key_backup = backup::encrypt_backup(&invalid_key_backup, "passphrase", 1);
+    assert_eq!(bedoza.restore_share(1, &invalid_key_backup, "passphrase"), Err(BackupError::Malformed));
+    //A negative share is out of range, and an iteration count above the maximum is refused before deriving the key
+    let mut negative_share_backup = backup::decrypt_backup(&backup, "correct horse battery staple").unwrap();
+    negative_share_backup.share = -negative_share_backup.share;
+    let negative_share_backup = backup::encrypt_backup(&negative_share_backup, "passphrase", 1);
+    assert_eq!(bedoza.restore_share(1, &negative_share_backup, "passphrase"), Err(BackupError::InconsistentShare));
+    let mut expensive_backup = backup.clone();
+    expensive_backup[5..9].copy_from_slice(&u32::MAX.to_be_bytes());
+    assert_eq!(bedoza.restore_share(1, &expensive_backup, "correct horse battery staple"), Err(BackupError::TooManyIterations(u32::MAX)));
+    //The rejected backups did not touch the share party 1 already held
+    assert_eq!(bedoza.open(sk.clone()), sk_value);
+    bedoza.set_active_parties(&[1, 2]);
+    assert_eq!(bedoza.open_ec(sk.clone()), pk.to_projective());
+
+    //A backup of a value the other parties no longer hold cannot be checked
+    bedoza.set_active_parties(&[0, 1, 2]);
+    bedoza.delete(sk.clone());
+    assert_eq!(bedoza.restore_share(1, &backup, "correct horse battery staple"), Err(BackupError::UnknownShare(sk)));
+}
+
+#[test]
+fn test_jws_es256() {
+    use base64::Engine;
+    let b64 = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory());
+    let (sk, pk) = tecdsa.gen_keypair();
+
+    let claims = serde_json::json!({"sub": "1234567890", "name": "Threshold", "iat": 1516239022});
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+    let token = tecdsa.sign_jwt(k, k_inv, sk_j_prime, &claims, Some("key-1")).unwrap();
+
+    let (header, payload) = tecdsa.verify_jws(pk, &token).unwrap();
+    assert_eq!(header, JwsHeader::jwt(Some("key-1")));
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&payload).unwrap(), claims);
+    let parts: Vec<&str> = token.split('.').collect();
+    let header_json: serde_json::Value = serde_json::from_slice(&b64.decode(parts[0]).unwrap()).unwrap();
+    assert_eq!(header_json, serde_json::json!({"alg": "ES256", "typ": "JWT", "kid": "key-1"}));
+
+    //The signature is a plain signature on the signing input, in the raw r||s format
+    let signing_input = format!("{}.{}", parts[0], parts[1]);
+    let raw_signature = b64.decode(parts[2]).unwrap();
+    assert_eq!(raw_signature.len(), 64);
+    let signature = Signature::from_bytes(&raw_signature, tecdsa.zp_field()).unwrap();
+    assert!(tecdsa.verify_signature(pk, &signing_input, signature));
+    let verifying_key = VerifyingKey::from_affine(pk.to_affine()).unwrap();
+    assert!(verifying_key.verify(signing_input.as_bytes(), &p256::ecdsa::Signature::from_slice(&raw_signature).unwrap()).is_ok());
+
+    //Tampered tokens and other algorithms are rejected
+    let tampered_payload = b64.encode(br#"{"sub":"admin"}"#);
+    let tampered = format!("{}.{}.{}", parts[0], tampered_payload, parts[2]);
+    assert_eq!(tecdsa.verify_jws(pk, &tampered), Err(JwsError::InvalidSignature(VerificationError::SignatureMismatch)));
+    let none_header = b64.encode(br#"{"alg":"none"}"#);
+    assert_eq!(tecdsa.verify_jws(pk, &format!("{}.{}.", none_header, parts[1])), Err(JwsError::UnsupportedAlgorithm("none".to_string())));
+    assert_eq!(tecdsa.verify_jws(pk, "not a token"), Err(JwsError::Malformed));
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+    let hs256_header = JwsHeader { alg: "HS256".to_string(), ..JwsHeader::jwt(None) };
+    assert_eq!(tecdsa.sign_jws(k, k_inv, sk_j_prime, &hs256_header, b"{}"), Err(SigningError::Jws(JwsError::UnsupportedAlgorithm("HS256".to_string()))));
+
+    //The JWK contains the coordinates of the public key
+    let jwk = pk.to_jwk();
+    assert_eq!(jwk["kty"], "EC");
+    assert_eq!(jwk["crv"], "P-256");
+    let uncompressed = pk.to_sec1_bytes(false);
+    assert_eq!(b64.decode(jwk["x"].as_str().unwrap()).unwrap(), uncompressed[1..33].to_vec());
+    assert_eq!(PublicKey::from_jwk(&jwk), Ok(pk));
+    let mut wrong_curve = jwk.clone();
+    wrong_curve["crv"] = serde_json::json!("P-384");
+    assert_eq!(PublicKey::from_jwk(&wrong_curve), Err(PublicKeyError::InvalidJwk));
+}
+
+#[test]
+fn test_x509_csr_and_self_signed_certificate() {
+    use x509_cert::der::{Decode, Encode};
+    use x509_cert::ext::pkix::{BasicConstraints, KeyUsage as ParsedKeyUsage, KeyUsages};
+    use x509_cert::request::CertReq;
+    use x509_cert::Certificate;
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory());
+    let (sk, pk) = tecdsa.gen_keypair();
+    let verifying_key = VerifyingKey::from_affine(pk.to_affine()).unwrap();
+    let subject = DistinguishedName { common_name: "threshold.example".to_string(), organization: Some("Example Org".to_string()), country: Some("DK".to_string()) };
+
+    //The request parses, contains the key and is signed by it
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+    let csr = tecdsa.create_csr(k, k_inv, sk_j_prime, &subject, &pk).unwrap();
+    let request = CertReq::from_der(&csr).unwrap();
+    assert_eq!(request.info.subject.to_string(), "CN=threshold.example,O=Example Org,C=DK");
+    assert_eq!(request.info.public_key.to_der().unwrap(), pk.to_public_key_der());
+    assert_eq!(request.algorithm.oid.to_string(), "1.2.840.10045.4.3.2");
+    let signature = p256::ecdsa::Signature::from_der(request.signature.raw_bytes()).unwrap();
+    assert!(verifying_key.verify(&request.info.to_der().unwrap(), &signature).is_ok());
+    assert!(x509::to_pem("CERTIFICATE REQUEST", &csr).starts_with("-----BEGIN CERTIFICATE REQUEST-----\n"));
+
+    //2050 and later must be encoded as GeneralizedTime
+    let not_before = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let not_after = SystemTime::UNIX_EPOCH + Duration::from_secs(2_600_000_000);
+    let params = CertificateParams {
+        subject: subject.clone(),
+        serial_number: BigInt::from(0x8000_0001u64),
+        not_before,
+        not_after,
+        key_usage: KeyUsage { digital_signature: true, key_cert_sign: true, ..KeyUsage::default() },
+        is_ca: true,
+    };
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+    let der = tecdsa.create_self_signed_certificate(k, k_inv, sk_j_prime, &params, &pk).unwrap();
+    let certificate = Certificate::from_der(&der).unwrap();
+    let tbs = &certificate.tbs_certificate;
+    assert_eq!(tbs.version, x509_cert::Version::V3);
+    assert_eq!(tbs.serial_number.as_bytes(), &[0x00, 0x80, 0x00, 0x00, 0x01]);
+    assert_eq!(tbs.issuer, tbs.subject);
+    assert_eq!(tbs.validity.not_before.to_system_time(), not_before);
+    assert_eq!(tbs.validity.not_after.to_system_time(), not_after);
+    assert!(matches!(tbs.validity.not_after, x509_cert::time::Time::GeneralTime(_)));
+    assert_eq!(tbs.subject_public_key_info.to_der().unwrap(), pk.to_public_key_der());
+    let (_, basic_constraints) = tbs.get::<BasicConstraints>().unwrap().unwrap();
+    assert!(basic_constraints.ca);
+    let (critical, key_usage) = tbs.get::<ParsedKeyUsage>().unwrap().unwrap();
+    assert!(critical);
+    assert_eq!(key_usage.0, KeyUsages::DigitalSignature | KeyUsages::KeyCertSign);
+    let signature = p256::ecdsa::Signature::from_der(certificate.signature.raw_bytes()).unwrap();
+    assert!(verifying_key.verify(&tbs.to_der().unwrap(), &signature).is_ok());
+
+    //A key which does not belong to the tuple is refused, and so is an empty key usage
+    let (_, other_pk) = tecdsa.gen_keypair();
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+    assert_eq!(tecdsa.create_csr(k, k_inv, sk_j_prime.clone(), &subject, &other_pk), Err(SigningError::KeyMismatch(sk_j_prime)));
+    let no_key_usage = CertificateParams { key_usage: KeyUsage::default(), ..params.clone() };
+    assert!(x509::tbs_certificate(&no_key_usage, &pk).is_err());
+    //Other invalid parameters are errors as well
+    let zero_serial = CertificateParams { serial_number: BigInt::from(0), ..params.clone() };
+    assert_eq!(x509::tbs_certificate(&zero_serial, &pk), Err(CertificateError::NonPositiveSerialNumber));
+    let expired = CertificateParams { not_after: params.not_before - Duration::from_secs(1), ..params.clone() };
+    assert_eq!(x509::tbs_certificate(&expired, &pk), Err(CertificateError::InvalidValidity));
+    let before_1970 = CertificateParams { not_before: UNIX_EPOCH - Duration::from_secs(1), ..params.clone() };
+    assert_eq!(x509::tbs_certificate(&before_1970, &pk), Err(CertificateError::TimeBefore1970));
+    let bad_country = DistinguishedName { country: Some("DNK".to_string()), ..subject.clone() };
+    assert_eq!(x509::certification_request_info(&bad_country, &pk), Err(CertificateError::InvalidCountry("DNK".to_string())));
+}
+
+#[test]
+fn test_ssh_keys_sshsig_and_agent() {
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory());
+    let pk = tecdsa.gen_user_keypair("alice", BTreeMap::new()).unwrap();
+
+    //The public key line round trips
+    let line = pk.to_ssh_public_key("alice@threshold");
+    assert!(line.starts_with("ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABB"));
+    assert!(line.ends_with(" alice@threshold"));
+    assert_eq!(PublicKey::from_ssh_public_key(&line), Ok(pk));
+    assert_eq!(PublicKey::from_ssh_public_key("ssh-ed25519 AAAA"), Err(SshError::UnsupportedKeyType("ssh-ed25519".to_string())));
+
+    //An sshsig signature verifies for its namespace and message only
+    let message = b"tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n";
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing_for_user("alice", presignature.k, presignature.k_inv).unwrap();
+    let armored = tecdsa.sign_sshsig(k, k_inv, sk_j_prime, pk, "git", message).unwrap();
+    assert!(armored.starts_with("-----BEGIN SSH SIGNATURE-----\n"));
+    assert!(armored.lines().all(|line| line.len() <= 70));
+    assert_eq!(tecdsa.verify_sshsig(&armored, "git", message), Ok(pk));
+    assert_eq!(tecdsa.verify_sshsig(&armored, "file", message), Err(SshError::NamespaceMismatch("git".to_string())));
+    assert_eq!(tecdsa.verify_sshsig(&armored, "git", b"another message"), Err(SshError::InvalidSignature(VerificationError::SignatureMismatch)));
+    //The key in the signature must be the key of the tuple
+    let (_, other_pk) = tecdsa.gen_keypair();
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing_for_user("alice", presignature.k, presignature.k_inv).unwrap();
+    assert_eq!(tecdsa.sign_sshsig(k, k_inv, sk_j_prime.clone(), other_pk, "git", message), Err(SigningError::KeyMismatch(sk_j_prime)));
+    //An empty namespace is refused before the tuple is used
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing_for_user("alice", presignature.k, presignature.k_inv).unwrap();
+    assert_eq!(tecdsa.sign_sshsig(k.clone(), k_inv.clone(), sk_j_prime.clone(), pk, "", message), Err(SigningError::Ssh(SshError::EmptyNamespace)));
+    assert!(tecdsa.sign_sshsig(k, k_inv, sk_j_prime, pk, "git", message).is_ok());
+    assert_eq!(tecdsa.verify_sshsig(&armored, "", message), Err(SshError::NamespaceMismatch("git".to_string())));
+
+    //The agent lists the keys of the keyring and signs with them over a Unix socket
+    #[cfg(unix)]
+    {
+        use std::io::{Read, Write};
+        use std::os::unix::net::{UnixListener, UnixStream};
+        let socket_path = std::env::temp_dir().join(format!("cc-ssh-agent-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        let key_blob = pk.to_ssh_key_blob();
+        let client = std::thread::spawn({
+            let socket_path = socket_path.clone();
+            let key_blob = key_blob.clone();
+            move || {
+                let mut stream = UnixStream::connect(socket_path).unwrap();
+                let mut request = |message: Vec<u8>| {
+                    stream.write_all(&(message.len() as u32).to_be_bytes()).unwrap();
+                    stream.write_all(&message).unwrap();
+                    let mut length = [0u8; 4];
+                    stream.read_exact(&mut length).unwrap();
+                    let mut response = vec![0u8; u32::from_be_bytes(length) as usize];
+                    stream.read_exact(&mut response).unwrap();
+                    response
+                };
+                let string = |bytes: &[u8]| [&(bytes.len() as u32).to_be_bytes()[..], bytes].concat();
+                let identities = request(vec![11]);
+                let sign_response = request([&[13][..], &string(&key_blob), &string(b"session data"), &[0, 0, 0, 0]].concat());
+                let unknown_key = request([&[13][..], &string(b"unknown"), &string(b"session data"), &[0, 0, 0, 0]].concat());
+                let unsupported = request(vec![17]);
+                (identities, sign_response, unknown_key, unsupported)
+            }
+        });
+        let (stream, _) = listener.accept().unwrap();
+        SshAgent::new(&mut tecdsa).handle_connection(stream).unwrap();
+        let (identities, sign_response, unknown_key, unsupported) = client.join().unwrap();
+        std::fs::remove_file(&socket_path).unwrap();
+
+        let comment = b"alice";
+        let expected_identities = [&[12, 0, 0, 0, 1, 0, 0, 0, key_blob.len() as u8][..], &key_blob, &[0, 0, 0, comment.len() as u8], comment].concat();
+        assert_eq!(identities, expected_identities);
+        assert_eq!(sign_response[0], 14);
+        assert_eq!(tecdsa.verify_ssh_signature(pk, b"session data", &sign_response[5..]), Ok(()));
+        assert_eq!(unknown_key, vec![5]);
+        assert_eq!(unsupported, vec![5]);
+    }
+}
+
+#[test]
+fn test_hedged_nonces() {
+    let (common_group, _) = load_groups();
+    let zp_field = ZpField::from_curve_order::<NistP256>();
+
+    //Even with no fresh randomness at all, the output depends on the key share and the context
+    let zero_randomness = [0u8; 32];
+    let share = BigInt::from(123456789);
+    let generate = |key_share: Option<&BigInt>, context: &[u8], randomness: &[u8]| HedgedGenerator::from_randomness(&zp_field, key_share, context, randomness).next_element();
+    let nonce = generate(Some(&share), b"session 1", &zero_randomness);
+    assert!(nonce > BigInt::from(0) && nonce < zp_field.p);
+    assert_eq!(generate(Some(&share), b"session 1", &zero_randomness), nonce);
+    assert_ne!(generate(Some(&share), b"session 2", &zero_randomness), nonce);
+    assert_ne!(generate(Some(&BigInt::from(987654321)), b"session 1", &zero_randomness), nonce);
+    assert_ne!(generate(None, b"session 1", &zero_randomness), nonce);
+    assert_ne!(generate(Some(&share), b"session 1", &[1u8; 32]), nonce);
+    let mut generator = HedgedGenerator::from_randomness(&zp_field, Some(&share), b"session 1", &zero_randomness);
+    assert_eq!(generator.next_element(), nonce);
+    assert_ne!(generator.next_element(), nonce);
+
+    //The first key becomes the hedging key, and the hedged presignatures give valid signatures
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory());
+    assert_eq!(tecdsa.hedging_key(), None);
+    let pk = tecdsa.gen_user_keypair("alice", BTreeMap::new()).unwrap();
+    let sk = tecdsa.keyring().get("alice").unwrap().sk.clone();
+    assert_eq!(tecdsa.hedging_key(), Some(&sk));
+    let (_, other_pk) = tecdsa.gen_keypair();
+    assert_eq!(tecdsa.hedging_key(), Some(&sk));
+    let (k, k_inv) = tecdsa.user_independent_preprocessing();
+    let (k2, _) = tecdsa.user_independent_preprocessing();
+    assert_ne!(tecdsa.bedoza().open_ec(k.clone()), tecdsa.bedoza().open_ec(k2));
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk, k, k_inv);
+    let signature = tecdsa.sign(k, k_inv, sk_j_prime, "hedged").unwrap();
+    assert!(tecdsa.verify_signature(pk, "hedged", signature.clone()));
+    assert!(!tecdsa.verify_signature(other_pk, "hedged", signature));
+
+    //Deleting the hedging key falls back to another key in the keyring, and without any key the presignatures are marked as not hedged
+    tecdsa.gen_user_keypair("bob", BTreeMap::new()).unwrap();
+    let bob_sk = tecdsa.keyring().get("bob").unwrap().sk.clone();
+    assert!(tecdsa.take_presignature().hedged);
+    tecdsa.delete_user_key("alice").unwrap();
+    assert_eq!(tecdsa.hedging_key(), Some(&bob_sk));
+    tecdsa.delete_user_key("bob").unwrap();
+    assert_eq!(tecdsa.hedging_key(), None);
+    tecdsa.configure_presignature_pool(2, 0);
+    assert!(!tecdsa.take_presignature().hedged);
+
+    //With Shamir sharing the parties hedge the polynomials of their contributions as well
+    let mut tecdsa = ThresholdECDSA::new_shamir(common_group, 3, 1, AuditLog::in_memory());
+    let (sk, pk) = tecdsa.gen_keypair();
+    tecdsa.set_active_parties(&[0, 2]);
+    let (k, k_inv) = tecdsa.user_independent_preprocessing();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), k, k_inv);
+    let signature = tecdsa.sign(k, k_inv, sk_j_prime, "hedged").unwrap();
+    assert!(tecdsa.verify_signature(pk, "hedged", signature));
+
+    //A party which was inactive when the hedging key was generated holds no share of it, so the key is not used while that party is active
+    tecdsa.set_active_parties(&[0, 1]);
+    let (later_sk, _) = tecdsa.gen_keypair();
+    tecdsa.set_hedging_key(Some(later_sk.clone()));
+    tecdsa.set_active_parties(&[0, 2]);
+    assert_eq!(tecdsa.hedging_key(), None);
+    assert!(!tecdsa.take_presignature().hedged);
+    tecdsa.set_active_parties(&[0, 1]);
+    assert_eq!(tecdsa.hedging_key(), Some(&later_sk));
+}
+
+#[test]
+fn test_commit_then_reveal_key_generation() {
+    let (common_group, _) = load_groups();
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 3, AuditLog::in_memory());
+    let zp_field = tecdsa.zp_field().clone();
+
+    //An honest run gives the same public key as opening the EC shares
+    let session = tecdsa.dkg_commit();
+    assert_eq!(session.commitments.len(), 3);
+    let reveals = tecdsa.dkg_reveal(&session).unwrap();
+    for (commitment, reveal) in session.commitments.iter().zip(&reveals) {
+        assert_eq!(dkg::check_reveal(&zp_field, commitment, reveal, &session.session_id), Ok(()));
+    }
+    let (sk, pk) = tecdsa.dkg_finish(session, &reveals).unwrap();
+    assert_eq!(pk.to_projective(), tecdsa.bedoza().open_ec(sk.clone()));
+
+    //A party revealing another share than it committed to is caught
+    let session = tecdsa.dkg_commit();
+    let mut reveals = tecdsa.dkg_reveal(&session).unwrap();
+    reveals[1].ec_share += ProjectivePoint::GENERATOR;
+    let sk = session.sk.clone();
+    assert_eq!(tecdsa.dkg_finish(session, &reveals), Err(DkgError::CommitmentMismatch(1)));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| tecdsa.bedoza().open_ec(sk.clone())));
+    assert!(result.is_err());
+
+    //A rogue share X_2 = target - X_0 - X_1 can only be chosen after the other reveals, and even when its commitment is swapped in the last party cannot prove knowledge of it
+    let mut session = tecdsa.dkg_commit();
+    let mut reveals = tecdsa.dkg_reveal(&session).unwrap();
+    let target = ProjectivePoint::GENERATOR * p256::Scalar::from(42u64);
+    reveals[2].ec_share = target - reveals[0].ec_share - reveals[1].ec_share;
+    session.commitments[2] = dkg::commit(&zp_field, &reveals[2], &session.session_id);
+    assert_eq!(tecdsa.dkg_finish(session, &reveals), Err(DkgError::InvalidProof(2)));
+
+    //Copying the reveal of another party does not work either, since the proof is bound to the index of the party
+    let mut session = tecdsa.dkg_commit();
+    let mut reveals = tecdsa.dkg_reveal(&session).unwrap();
+    reveals[2] = reveals[0].clone();
+    reveals[2].party = 2;
+    session.commitments[2] = dkg::commit(&zp_field, &reveals[2], &session.session_id);
+    assert_eq!(tecdsa.dkg_finish(session, &reveals), Err(DkgError::InvalidProof(2)));
+
+    //The parties only reveal once every active party has committed, and only once per session
+    let mut session = tecdsa.dkg_commit();
+    session.commitments.remove(0);
+    assert_eq!(tecdsa.dkg_reveal(&session), Err(DkgError::MissingCommitment(0)));
+    assert_eq!(tecdsa.dkg_reveal(&session), Err(DkgError::AlreadyRevealed));
+    let session = tecdsa.dkg_commit();
+    let reveals = tecdsa.dkg_reveal(&session).unwrap();
+    assert_eq!(tecdsa.dkg_reveal(&session), Err(DkgError::AlreadyRevealed));
+    assert!(tecdsa.dkg_finish(session, &reveals).is_ok());
+
+    //With Shamir sharing the revealed shares are combined using the Lagrange coefficients of the active parties
+    let mut tecdsa = ThresholdECDSA::new_shamir(common_group, 3, 1, AuditLog::in_memory());
+    tecdsa.set_active_parties(&[0, 2]);
+    let (sk, pk) = tecdsa.gen_keypair();
+    assert_eq!(pk.to_projective(), tecdsa.bedoza().open_ec(sk.clone()));
+    let (k, k_inv) = tecdsa.user_independent_preprocessing();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk, k, k_inv);
+    let signature = tecdsa.sign(k, k_inv, sk_j_prime, "dkg").unwrap();
+    assert!(tecdsa.verify_signature(pk, "dkg", signature));
+}
+
+#[test]
+fn test_audit_log_and_nonce_reuse() {
+    use elliptic_curve::point::AffineCoordinates;
+    let (common_group, zp_field) = load_groups();
+    let log_path = std::env::temp_dir().join(format!("cc-audit-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&log_path);
+
+    let mut tecdsa = ThresholdECDSA::new(common_group.clone(), 3, AuditLog::open(&log_path).unwrap());
+    let (sk, pk) = tecdsa.gen_keypair();
+    let (other_sk, _) = tecdsa.gen_keypair();
+    let before = SystemTime::now();
+    let mut signatures = vec![];
+    for (key, message) in [(&sk, "first"), (&other_sk, "second"), (&sk, "third")] {
+        let presignature = tecdsa.take_presignature();
+        let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(key.clone(), presignature.k, presignature.k_inv);
+        signatures.push(tecdsa.sign(k, k_inv, sk_j_prime, message).unwrap());
+    }
+    assert!(tecdsa.verify_signature(pk, "third", signatures[2].clone()));
+
+    //A nonce refused by a policy is recorded with its outcome, while a batch entry preprocessed with another key is refused before its nonce is opened
+    let mut policy = SigningPolicy::new();
+    policy.add_key_rule(sk.clone(), PolicyRule::MessageAllowlist(vec![b"first".to_vec(), b"third".to_vec()]));
+    tecdsa.set_signing_policy(1, policy);
+    let presignature = tecdsa.take_presignature();
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+    assert!(matches!(tecdsa.sign(k, k_inv, sk_j_prime, "refused"), Err(SigningError::PolicyRefused(_))));
+    let presignature = tecdsa.take_presignature();
+    let tuple = tecdsa.user_dependent_preprocessing(other_sk.clone(), presignature.k, presignature.k_inv);
+    let sk_j_prime = tuple.2.clone();
+    assert_eq!(tecdsa.sign_batch(pk, &[(tuple, b"batch")]), vec![Err(SigningError::KeyMismatch(sk_j_prime))]);
+
+    //Every nonce has a record, which can be queried by key, message hash, outcome and time
+    let log = tecdsa.audit_log();
+    assert_eq!(log.len(), 4);
+    let produced_by_sk = log.query(&AuditQuery { key: Some(sk.clone()), outcome: Some(AuditOutcome::Produced), ..AuditQuery::default() });
+    assert_eq!(produced_by_sk.iter().map(|record| record.r.clone()).collect::<Vec<_>>(), vec![signatures[0].r.clone(), signatures[2].r.clone()]);
+    let message_hash = cc::threshold_ecdsa::hashing::hash_bytes(b"second", HashAlgorithm::Sha256, zp_field.clone());
+    let records_of_message = log.query(&AuditQuery { message_hash: Some(message_hash.clone()), ..AuditQuery::default() });
+    assert_eq!(records_of_message.len(), 1);
+    assert_eq!(records_of_message[0].key, other_sk);
+    assert_eq!(log.query(&AuditQuery { since: Some(before), ..AuditQuery::default() }).len(), 4);
+    assert!(log.query(&AuditQuery { until: Some(before), ..AuditQuery::default() }).is_empty());
+    assert_eq!(log.find_by_r(&signatures[1].r).unwrap().message_hash, message_hash);
+    let outcomes: Vec<AuditOutcome> = log.records().iter().map(|record| record.outcome).collect();
+    assert_eq!(outcomes[3..], [AuditOutcome::Refused]);
+
+    //The file is locked while the log is open
+    assert_eq!(AuditLog::open(&log_path).unwrap_err().kind(), std::io::ErrorKind::WouldBlock);
+    drop(tecdsa);
+
+    //The log is persistent, and a nonce with the r of an earlier signature is refused before s is opened
+    let mut tecdsa = ThresholdECDSA::new(common_group, 3, AuditLog::in_memory());
+    let (sk, _) = tecdsa.gen_keypair();
+    let presignature = tecdsa.take_presignature();
+    let r_point = tecdsa.bedoza().open_ec(presignature.k.clone()).to_affine();
+    let r = zp_field.create_field_element(BigInt::from_bytes_be(num_bigint::Sign::Plus, &r_point.x()));
+    let mut log = AuditLog::open(&log_path).unwrap();
+    assert_eq!(log.len(), 4);
+    assert_eq!(log.records().iter().map(|record| record.outcome).collect::<Vec<_>>(), outcomes);
+    log.append(AuditRecord { key: "earlier key".to_string(), r: r.clone(), message_hash: BigInt::from(1), timestamp: SystemTime::now(), outcome: AuditOutcome::Pending }).unwrap();
+    let duplicate = AuditRecord { key: "another key".to_string(), r: r.clone(), message_hash: BigInt::from(2), timestamp: SystemTime::now(), outcome: AuditOutcome::Pending };
+    assert_eq!(log.append(duplicate), Err(AuditError::NonceReuse(r.clone())));
+    drop(log);
+    tecdsa.set_audit_log(AuditLog::open(&log_path).unwrap());
+    assert_eq!(tecdsa.audit_log().len(), 5);
+    let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk, presignature.k, presignature.k_inv);
+    let rounds_before = tecdsa.bedoza().opening_rounds();
+    assert_eq!(tecdsa.sign(k, k_inv, sk_j_prime, "reused"), Err(SigningError::Audit(AuditError::NonceReuse(r))));
+    //Only R has been opened
+    assert_eq!(tecdsa.bedoza().opening_rounds() - rounds_before, 1);
+    assert_eq!(tecdsa.audit_log().len(), 5);
+
+    //Every record is written when R is opened, and again when its outcome is known
+    let contents = std::fs::read_to_string(&log_path).unwrap();
+    assert_eq!(contents.lines().count(), 9);
+    let first: serde_json::Value = serde_json::from_str(contents.lines().next().unwrap()).unwrap();
+    assert_eq!(first["r"], format!("{:x}", signatures[0].r));
+    assert_eq!(first["outcome"], "Pending");
+
+    //A line torn by a crash is removed when the log is opened again, and only records in the log can get an outcome
+    drop(tecdsa);
+    std::fs::write(&log_path, format!("{}{}", contents, &contents.lines().next().unwrap()[..20])).unwrap();
+    let mut log = AuditLog::open(&log_path).unwrap();
+    assert_eq!(log.len(), 5);
+    assert_eq!(std::fs::read_to_string(&log_path).unwrap(), contents);
+    assert_eq!(log.set_outcome(&BigInt::from(1), AuditOutcome::Produced), Err(AuditError::UnknownNonce(BigInt::from(1))));
+    std::fs::remove_file(&log_path).unwrap();
+}
+
+#[test]
+fn test_verify_batch() {
+    let (common_group, zp_field) = load_groups();
+
+    //The multi-scalar multiplication agrees with separate scalar multiplications
+    let terms: Vec<(BigInt, ProjectivePoint)> = (1..40u64).map(|i| {
+        (zp_field.generate_random_element(), ProjectivePoint::GENERATOR * p256::Scalar::from(i * i + 7))
+    }).collect();
+    let expected = terms.iter().fold(ProjectivePoint::IDENTITY, |acc, (scalar, point)| acc + *point * ec_helpers::bigint_to_scalar::<NistP256>(scalar.clone()));
+    assert_eq!(ec_helpers::multi_scalar_mul::<NistP256>(&terms), expected);
+    assert_eq!(ec_helpers::multi_scalar_mul::<NistP256>(&[]), ProjectivePoint::IDENTITY);
+
+    let mut tecdsa = ThresholdECDSA::new(common_group, 3, AuditLog::in_memory());
+    tecdsa.set_low_s_normalization(true);
+    let keys = [tecdsa.gen_keypair(), tecdsa.gen_keypair()];
+    let messages: Vec<Vec<u8>> = (0..10).map(|i| format!("message {}", i).into_bytes()).collect();
+    let mut entries = Vec::new();
+    for (i, message) in messages.iter().enumerate() {
+        let (sk, pk) = &keys[i % 2];
+        let presignature = tecdsa.take_presignature();
+        let (k, k_inv, sk_j_prime) = tecdsa.user_dependent_preprocessing(sk.clone(), presignature.k, presignature.k_inv);
+        let (signature, recovery_id) = tecdsa.sign_recoverable(k, k_inv, sk_j_prime, message).unwrap();
+        entries.push(BatchEntry { pk: *pk, message, signature, recovery_id });
+    }
+    assert_eq!(tecdsa.verify_batch(&entries), Ok(()));
+    assert_eq!(tecdsa.verify_batch(&[]), Ok(()));
+
+    //The bad entries are found when the batch fails
+    let mut bad_entries = entries.clone();
+    bad_entries[3].message = b"forged";
+    bad_entries[8].pk = keys[1].1;
+    bad_entries[6].signature = Signature::new(bad_entries[6].signature.r.clone(), BigInt::from(0));
+    assert_eq!(tecdsa.verify_batch(&bad_entries), Err(vec![
+        (3, VerificationError::SignatureMismatch),
+        (6, VerificationError::ScalarOutOfRange),
+        (8, VerificationError::SignatureMismatch),
+    ]));
+
+    //A valid signature with a wrong recovery id is still accepted
+    let mut wrong_recovery_id = entries.clone();
+    let recovery_id = wrong_recovery_id[5].recovery_id;
+    wrong_recovery_id[5].recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+    assert_eq!(tecdsa.verify_batch(&wrong_recovery_id), Ok(()));
+}